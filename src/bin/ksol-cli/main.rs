@@ -0,0 +1,478 @@
+//! Drives the staking program from the command line, built with the `cli` feature.
+mod rpc;
+
+use clap::{crate_version, App, AppSettings, Arg, ArgMatches, SubCommand};
+use rpc::{Result, RpcClient};
+use solana_sdk::{
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::{read_keypair_file, Keypair, Signer},
+    system_instruction,
+    transaction::Transaction,
+};
+use staking::{
+    event::Event,
+    get_airdrop_vesting_address, indexer, instruction,
+    processor::{BASE_COIN, KITTYCOIN_MINT},
+    state::{AccountState, RewardsPool, Stake, VestingSchedule},
+};
+use std::str::FromStr;
+
+const DECIMALS: usize = 6;
+
+struct Config {
+    rpc: RpcClient,
+    payer: Keypair,
+    program_id: Pubkey,
+}
+
+fn main() {
+    let matches = app().get_matches();
+    if let Err(err) = run(&matches) {
+        eprintln!("error: {}", err);
+        std::process::exit(1);
+    }
+}
+
+fn app() -> App<'static, 'static> {
+    let pool = Arg::with_name("pool")
+        .long("pool")
+        .value_name("ADDRESS")
+        .takes_value(true)
+        .required(true)
+        .help("Rewards pool account");
+    let amount = Arg::with_name("amount")
+        .long("amount")
+        .value_name("KITTYCOIN")
+        .takes_value(true)
+        .required(true)
+        .help("Amount in Kittycoin, up to 6 decimals");
+
+    App::new("ksol-cli")
+        .version(crate_version!())
+        .about("Staking, voting and admin operations for the Kittycoin staking program")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .arg(
+            Arg::with_name("url")
+                .long("url")
+                .short("u")
+                .value_name("URL")
+                .takes_value(true)
+                .global(true)
+                .default_value("http://localhost:8899")
+                .help("JSON RPC endpoint"),
+        )
+        .arg(
+            Arg::with_name("keypair")
+                .long("keypair")
+                .short("k")
+                .value_name("PATH")
+                .takes_value(true)
+                .global(true)
+                .help("Keypair paying for and signing transactions [default: ~/.config/solana/id.json]"),
+        )
+        .arg(
+            Arg::with_name("program_id")
+                .long("program-id")
+                .value_name("ADDRESS")
+                .takes_value(true)
+                .global(true)
+                .env("KSOL_PROGRAM_ID")
+                .help("Deployed staking program"),
+        )
+        .subcommand(
+            SubCommand::with_name("stake")
+                .about("Stake coins in a new stake account")
+                .arg(pool.clone())
+                .arg(amount.clone()),
+        )
+        .subcommand(
+            SubCommand::with_name("unstake")
+                .about("End a stake and collect its reward")
+                .arg(pool.clone())
+                .arg(
+                    Arg::with_name("stake")
+                        .long("stake")
+                        .value_name("ADDRESS")
+                        .takes_value(true)
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("emergency")
+                        .long("emergency")
+                        .help("Return the principal only, works while the pool is paused"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("claim")
+                .about("Release vested airdrop coins, or withdraw from a vesting contract")
+                .arg(pool.clone())
+                .arg(
+                    Arg::with_name("vesting_id")
+                        .long("vesting-id")
+                        .value_name("ID")
+                        .takes_value(true)
+                        .help("Withdraw from the vesting contract with this id instead"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("vote")
+                .about("Vote on a ballot, pays the voting fee into the pool")
+                .arg(pool.clone())
+                .arg(
+                    Arg::with_name("ballot")
+                        .long("ballot")
+                        .value_name("ADDRESS")
+                        .takes_value(true)
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("choice")
+                        .long("choice")
+                        .value_name("0-4")
+                        .takes_value(true)
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("airdrop")
+                .about("Claim an airdrop")
+                .arg(pool.clone())
+                .arg(amount.clone()),
+        )
+        .subcommand(
+            SubCommand::with_name("pool-info")
+                .about("Show a rewards pool")
+                .arg(pool.clone())
+                .arg(
+                    Arg::with_name("stake")
+                        .long("stake")
+                        .value_name("ADDRESS")
+                        .takes_value(true)
+                        .help("Also show the reward this stake would collect now"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("add-rewards")
+                .about("Fund the pool's rewards, or its airdrop supply")
+                .arg(pool.clone())
+                .arg(amount)
+                .arg(Arg::with_name("airdrop").long("airdrop")),
+        )
+        .subcommand(
+            SubCommand::with_name("set-fee")
+                .about("Change the airdrop fee")
+                .arg(pool)
+                .arg(
+                    Arg::with_name("fee")
+                        .long("fee")
+                        .value_name("AMOUNT")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Lamports, or base units of the pool's fee mint"),
+                ),
+        )
+}
+
+fn run(matches: &ArgMatches) -> Result<()> {
+    //Global arguments are only complete on the subcommand's matches.
+    let (command, args) = matches.subcommand();
+    let args = args.unwrap();
+
+    let keypair_path = match args.value_of("keypair") {
+        Some(path) => path.to_string(),
+        None => format!("{}/.config/solana/id.json", std::env::var("HOME")?),
+    };
+    let config = Config {
+        rpc: RpcClient::new(args.value_of("url").unwrap()),
+        payer: read_keypair_file(&keypair_path)
+            .map_err(|err| format!("Cannot read keypair {}: {}", keypair_path, err))?,
+        program_id: pubkey_of(args, "program_id")?,
+    };
+
+    match command {
+        "stake" => stake(&config, args),
+        "unstake" => unstake(&config, args),
+        "claim" => claim(&config, args),
+        "vote" => vote(&config, args),
+        "airdrop" => airdrop(&config, args),
+        "pool-info" => pool_info(&config, args),
+        "add-rewards" => add_rewards(&config, args),
+        "set-fee" => set_fee(&config, args),
+        _ => unreachable!(),
+    }
+}
+
+fn stake(config: &Config, args: &ArgMatches) -> Result<()> {
+    let owner = config.payer.pubkey();
+    let stake = Keypair::new();
+    let lamports = config
+        .rpc
+        .get_minimum_balance_for_rent_exemption(Stake::LEN)?;
+
+    let instructions = vec![
+        system_instruction::create_account(
+            &owner,
+            &stake.pubkey(),
+            lamports,
+            Stake::LEN as u64,
+            &config.program_id,
+        ),
+        instruction::create_stake(
+            &config.program_id,
+            &owner,
+            &token_address(&owner),
+            &stake.pubkey(),
+            &pubkey_of(args, "pool")?,
+            amount_of(args, "amount")?,
+        ),
+    ];
+    send(config, instructions, &[&stake])?;
+    println!("Stake account: {}", stake.pubkey());
+    Ok(())
+}
+
+fn unstake(config: &Config, args: &ArgMatches) -> Result<()> {
+    let owner = config.payer.pubkey();
+    let builder = if args.is_present("emergency") {
+        instruction::emergency_withdraw
+    } else {
+        instruction::end_stake
+    };
+
+    let mut instructions = create_token_account_if_missing(config)?;
+    instructions.push(builder(
+        &config.program_id,
+        &owner,
+        &token_address(&owner),
+        &pubkey_of(args, "stake")?,
+        &pubkey_of(args, "pool")?,
+    ));
+    send(config, instructions, &[])
+}
+
+fn claim(config: &Config, args: &ArgMatches) -> Result<()> {
+    let owner = config.payer.pubkey();
+    let mut instructions = create_token_account_if_missing(config)?;
+    match args.value_of("vesting_id") {
+        Some(id) => instructions.push(instruction::withdraw(
+            &config.program_id,
+            &owner,
+            &token_address(&owner),
+            id.parse()?,
+        )),
+        None => {
+            let schedule = get_airdrop_vesting_address(&config.program_id, &owner);
+            let schedule: VestingSchedule = indexer::decode(&fetch(config, &schedule)?)?;
+            println!(
+                "Vesting {} of {} coins released so far",
+                format_amount(schedule.released),
+                format_amount(schedule.total)
+            );
+            instructions.push(instruction::release_vested(
+                &config.program_id,
+                &owner,
+                &token_address(&owner),
+                &pubkey_of(args, "pool")?,
+            ));
+        }
+    }
+    send(config, instructions, &[])
+}
+
+fn vote(config: &Config, args: &ArgMatches) -> Result<()> {
+    let owner = config.payer.pubkey();
+    let instructions = vec![instruction::vote(
+        &config.program_id,
+        &owner,
+        &token_address(&owner),
+        &pubkey_of(args, "ballot")?,
+        &pubkey_of(args, "pool")?,
+        args.value_of("choice").unwrap().parse()?,
+    )];
+    send(config, instructions, &[])
+}
+
+fn airdrop(config: &Config, args: &ArgMatches) -> Result<()> {
+    let owner = config.payer.pubkey();
+    let pool_address = pubkey_of(args, "pool")?;
+    let pool: RewardsPool = indexer::decode(&fetch(config, &pool_address)?)?;
+
+    //Fees in an SPL token are paid from the owner's account for that mint.
+    let owner_fee_token = if pool.airdrop_fee_mint == Pubkey::default() {
+        None
+    } else {
+        Some(spl_associated_token_account::get_associated_token_address(
+            &owner,
+            &pool.airdrop_fee_mint,
+        ))
+    };
+
+    let mut instructions = create_token_account_if_missing(config)?;
+    instructions.push(instruction::airdrop(
+        &config.program_id,
+        &owner,
+        &token_address(&owner),
+        &pool_address,
+        &pool.airdrop_treasury,
+        owner_fee_token.as_ref(),
+        amount_of(args, "amount")?,
+    ));
+    send(config, instructions, &[])
+}
+
+fn pool_info(config: &Config, args: &ArgMatches) -> Result<()> {
+    let pool: RewardsPool = indexer::decode(&fetch(config, &pubkey_of(args, "pool")?)?)?;
+
+    println!("Rewards:              {}", format_amount(pool.amount));
+    println!(
+        "Airdrop supply:       {}",
+        format_amount(pool.airdrop_supply)
+    );
+    println!(
+        "Staked:               {}",
+        format_amount(pool.total_coins_staked)
+    );
+    println!("Stakes:               {}", pool.total_stakes_count);
+    println!(
+        "Vesting locked:       {}",
+        format_amount(pool.vesting_locked)
+    );
+    println!("Airdrop fee:          {}", pool.airdrop_fee);
+    if pool.airdrop_fee_mint != Pubkey::default() {
+        println!("Airdrop fee mint:     {}", pool.airdrop_fee_mint);
+    }
+    println!("Airdrop treasury:     {}", pool.airdrop_treasury);
+    println!(
+        "Airdrop wallet cap:   {}",
+        format_amount(pool.airdrop_wallet_cap)
+    );
+    println!("Airdrop cooldown:     {}s", pool.airdrop_cooldown);
+    println!(
+        "Airdrop vesting:      {}s, {}s cliff",
+        pool.airdrop_vesting_duration, pool.airdrop_vesting_cliff
+    );
+    println!("Paused:               {:#06b}", pool.paused);
+
+    if let Some(stake) = args.value_of("stake") {
+        let stake: Stake = indexer::decode(&fetch(config, &Pubkey::from_str(stake)?)?)?;
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_secs() as i64;
+        println!("Stake:                {}", format_amount(stake.amount));
+        println!(
+            "Pending reward:       {}",
+            format_amount(indexer::pending_reward(&stake, &pool, now))
+        );
+    }
+    Ok(())
+}
+
+fn add_rewards(config: &Config, args: &ArgMatches) -> Result<()> {
+    let admin = config.payer.pubkey();
+    let instructions = vec![instruction::add_to_rewards_pool(
+        &config.program_id,
+        &admin,
+        &token_address(&admin),
+        &pubkey_of(args, "pool")?,
+        amount_of(args, "amount")?,
+        args.is_present("airdrop"),
+    )];
+    send(config, instructions, &[])
+}
+
+fn set_fee(config: &Config, args: &ArgMatches) -> Result<()> {
+    let instructions = vec![instruction::change_airdrop_fee(
+        &config.program_id,
+        &config.payer.pubkey(),
+        &[],
+        &pubkey_of(args, "pool")?,
+        args.value_of("fee").unwrap().parse()?,
+    )];
+    send(config, instructions, &[])
+}
+
+/// Signs with the payer and `signers`, then prints the signature and the program's events.
+fn send(config: &Config, instructions: Vec<Instruction>, signers: &[&Keypair]) -> Result<()> {
+    let mut all_signers = vec![&config.payer];
+    all_signers.extend_from_slice(signers);
+    let transaction = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&config.payer.pubkey()),
+        &all_signers,
+        config.rpc.get_latest_blockhash()?,
+    );
+    let (signature, logs) = config.rpc.send_and_confirm(&transaction)?;
+    println!("Signature: {}", signature);
+    for event in Event::from_logs(&config.program_id, &logs) {
+        println!("{:?}", event);
+    }
+    Ok(())
+}
+
+fn fetch(config: &Config, address: &Pubkey) -> Result<Vec<u8>> {
+    config
+        .rpc
+        .get_account_data(address)?
+        .ok_or_else(|| format!("Account {} not found", address).into())
+}
+
+fn token_address(owner: &Pubkey) -> Pubkey {
+    spl_associated_token_account::get_associated_token_address(
+        owner,
+        &Pubkey::new_from_array(KITTYCOIN_MINT),
+    )
+}
+
+/// Creates the payer's Kittycoin account first when a command pays coins out to it.
+fn create_token_account_if_missing(config: &Config) -> Result<Vec<Instruction>> {
+    let owner = config.payer.pubkey();
+    if config
+        .rpc
+        .get_account_data(&token_address(&owner))?
+        .is_some()
+    {
+        return Ok(vec![]);
+    }
+    Ok(vec![
+        spl_associated_token_account::create_associated_token_account(
+            &owner,
+            &owner,
+            &Pubkey::new_from_array(KITTYCOIN_MINT),
+        ),
+    ])
+}
+
+fn pubkey_of(args: &ArgMatches, name: &str) -> Result<Pubkey> {
+    let value = args
+        .value_of(name)
+        .ok_or_else(|| format!("--{} is required", name.replace('_', "-")))?;
+    Ok(Pubkey::from_str(value).map_err(|_| format!("Invalid address {}", value))?)
+}
+
+/// Parses a Kittycoin amount such as `1.5` into base units.
+fn amount_of(args: &ArgMatches, name: &str) -> Result<u64> {
+    let value = args.value_of(name).unwrap();
+    let (whole, fraction) = match value.find('.') {
+        Some(dot) => (&value[..dot], &value[dot + 1..]),
+        None => (value, ""),
+    };
+    if fraction.len() > DECIMALS {
+        return Err(format!("At most {} decimals: {}", DECIMALS, value).into());
+    }
+    let whole: u64 = if whole.is_empty() { 0 } else { whole.parse()? };
+    let fraction: u64 = format!("{:0<width$}", fraction, width = DECIMALS).parse()?;
+    whole
+        .checked_mul(BASE_COIN)
+        .and_then(|amount| amount.checked_add(fraction))
+        .ok_or_else(|| format!("Amount too large: {}", value).into())
+}
+
+fn format_amount(amount: u64) -> String {
+    format!(
+        "{}.{:0width$}",
+        amount / BASE_COIN,
+        amount % BASE_COIN,
+        width = DECIMALS
+    )
+}
@@ -0,0 +1,106 @@
+//! The handful of JSON-RPC calls the CLI needs.
+use serde_json::{json, Value};
+use solana_sdk::{hash::Hash, pubkey::Pubkey, signature::Signature, transaction::Transaction};
+use std::{error::Error, str::FromStr, thread, time::Duration};
+
+pub type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+const COMMITMENT: &str = "confirmed";
+const CONFIRM_RETRIES: usize = 60;
+
+pub struct RpcClient {
+    url: String,
+    http: reqwest::blocking::Client,
+}
+
+impl RpcClient {
+    pub fn new(url: &str) -> Self {
+        RpcClient {
+            url: url.to_string(),
+            http: reqwest::blocking::Client::new(),
+        }
+    }
+
+    fn call(&self, method: &str, params: Value) -> Result<Value> {
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": method,
+            "params": params,
+        });
+        let mut response: Value = self.http.post(&self.url).json(&request).send()?.json()?;
+        if let Some(error) = response.get("error") {
+            return Err(format!("{} failed: {}", method, error).into());
+        }
+        Ok(response["result"].take())
+    }
+
+    /// Account data, `None` when the account does not exist.
+    pub fn get_account_data(&self, address: &Pubkey) -> Result<Option<Vec<u8>>> {
+        let result = self.call(
+            "getAccountInfo",
+            json!([address.to_string(), {"encoding": "base64", "commitment": COMMITMENT}]),
+        )?;
+        match result["value"]["data"][0].as_str() {
+            Some(data) => Ok(Some(base64::decode(data)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn get_minimum_balance_for_rent_exemption(&self, len: usize) -> Result<u64> {
+        self.call("getMinimumBalanceForRentExemption", json!([len]))?
+            .as_u64()
+            .ok_or_else(|| "Unexpected rent response".into())
+    }
+
+    pub fn get_latest_blockhash(&self) -> Result<Hash> {
+        //Validators before 1.9 only know getRecentBlockhash.
+        let result = self
+            .call("getLatestBlockhash", json!([{"commitment": COMMITMENT}]))
+            .or_else(|_| self.call("getRecentBlockhash", json!([{"commitment": COMMITMENT}])))?;
+        let blockhash = result["value"]["blockhash"]
+            .as_str()
+            .ok_or("Unexpected blockhash response")?;
+        Ok(Hash::from_str(blockhash)?)
+    }
+
+    /// Sends a signed transaction and waits until it is confirmed, returns its log messages.
+    pub fn send_and_confirm(&self, transaction: &Transaction) -> Result<(Signature, Vec<String>)> {
+        let encoded = base64::encode(bincode::serialize(transaction)?);
+        let signature = self.call(
+            "sendTransaction",
+            json!([encoded, {"encoding": "base64", "preflightCommitment": COMMITMENT}]),
+        )?;
+        let signature = Signature::from_str(signature.as_str().ok_or("Unexpected signature")?)?;
+
+        for _ in 0..CONFIRM_RETRIES {
+            let statuses = self.call("getSignatureStatuses", json!([[signature.to_string()]]))?;
+            let status = &statuses["value"][0];
+            if !status["err"].is_null() {
+                return Err(format!("Transaction {} failed: {}", signature, status["err"]).into());
+            }
+            match status["confirmationStatus"].as_str() {
+                Some("confirmed") | Some("finalized") => {
+                    return Ok((signature, self.get_logs(&signature)?));
+                }
+                _ => thread::sleep(Duration::from_millis(500)),
+            }
+        }
+        Err(format!("Transaction {} was not confirmed", signature).into())
+    }
+
+    fn get_logs(&self, signature: &Signature) -> Result<Vec<String>> {
+        let result = self.call(
+            "getTransaction",
+            json!([signature.to_string(), {"encoding": "json", "commitment": COMMITMENT}]),
+        )?;
+        Ok(result["meta"]["logMessages"]
+            .as_array()
+            .map(|logs| {
+                logs.iter()
+                    .filter_map(|line| line.as_str().map(str::to_string))
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+}
@@ -0,0 +1,332 @@
+//! Helpers for programs that call into the staking program. Each one builds the instruction with
+//! the matching `instruction` builder and invokes it, signing with `signer_seeds` for any of the
+//! caller's PDAs among the accounts (pass `&[]` when there are none). Admin instructions are left
+//! to the builders and `invoke_signed`.
+//!
+//! Results come back through the return data, read them with `return_data::get`.
+use crate::instruction;
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program::invoke_signed,
+};
+
+pub struct CreateStakeAccounts<'a> {
+    pub owner: AccountInfo<'a>, //Funds the stake, and owns it unless `stake_owner` is set.
+    pub owner_token: AccountInfo<'a>,
+    pub stake: AccountInfo<'a>,
+    pub pda_token: AccountInfo<'a>,
+    pub token_program: AccountInfo<'a>,
+    pub rewards: AccountInfo<'a>,
+    pub stake_owner: Option<AccountInfo<'a>>, //Usually the calling program's PDA.
+}
+
+/// Accounts of `EndStake` and `EmergencyWithdraw`.
+pub struct EndStakeAccounts<'a> {
+    pub owner: AccountInfo<'a>,
+    pub owner_token: AccountInfo<'a>,
+    pub stake: AccountInfo<'a>,
+    pub pda: AccountInfo<'a>,
+    pub pda_token: AccountInfo<'a>,
+    pub token_program: AccountInfo<'a>,
+    pub rewards: AccountInfo<'a>,
+}
+
+impl<'a> EndStakeAccounts<'a> {
+    fn into_infos(self, program: AccountInfo<'a>) -> Vec<AccountInfo<'a>> {
+        vec![
+            self.owner,
+            self.owner_token,
+            self.stake,
+            self.pda,
+            self.pda_token,
+            self.token_program,
+            self.rewards,
+            program,
+        ]
+    }
+}
+
+pub struct AddToRewardsPoolAccounts<'a> {
+    pub donator: AccountInfo<'a>,
+    pub donator_token: AccountInfo<'a>,
+    pub pda_token: AccountInfo<'a>,
+    pub token_program: AccountInfo<'a>,
+    pub rewards: AccountInfo<'a>,
+    pub authority: AccountInfo<'a>,
+}
+
+pub struct VoteAccounts<'a> {
+    pub owner: AccountInfo<'a>,
+    pub owner_token: AccountInfo<'a>,
+    pub ballot: AccountInfo<'a>,
+    pub pda_token: AccountInfo<'a>,
+    pub token_program: AccountInfo<'a>,
+    pub rewards: AccountInfo<'a>,
+    pub authority: AccountInfo<'a>,
+}
+
+pub struct AirdropAccounts<'a> {
+    pub owner: AccountInfo<'a>,
+    pub owner_token: AccountInfo<'a>,
+    pub pda: AccountInfo<'a>,
+    pub pda_token: AccountInfo<'a>,
+    pub token_program: AccountInfo<'a>,
+    pub rewards: AccountInfo<'a>,
+    pub treasury: AccountInfo<'a>,
+    pub airdrop_receipt: AccountInfo<'a>,
+    pub system_program: AccountInfo<'a>,
+    pub vesting_schedule: AccountInfo<'a>,
+    pub owner_fee_token: Option<AccountInfo<'a>>, //Only when the fee is paid in an SPL token.
+}
+
+pub struct ReleaseVestedAccounts<'a> {
+    pub beneficiary: AccountInfo<'a>,
+    pub beneficiary_token: AccountInfo<'a>,
+    pub vesting_schedule: AccountInfo<'a>,
+    pub pda: AccountInfo<'a>,
+    pub pda_token: AccountInfo<'a>,
+    pub token_program: AccountInfo<'a>,
+    pub rewards: AccountInfo<'a>,
+}
+
+pub struct WithdrawAccounts<'a> {
+    pub beneficiary: AccountInfo<'a>,
+    pub beneficiary_token: AccountInfo<'a>,
+    pub vesting_contract: AccountInfo<'a>,
+    pub vesting_vault: AccountInfo<'a>,
+    pub token_program: AccountInfo<'a>,
+}
+
+pub fn create_stake<'a>(
+    program: AccountInfo<'a>,
+    accounts: CreateStakeAccounts<'a>,
+    amount: u64,
+    signer_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    let ix = match &accounts.stake_owner {
+        Some(stake_owner) => instruction::create_stake_for(
+            program.key,
+            accounts.owner.key,
+            accounts.owner_token.key,
+            stake_owner.key,
+            accounts.stake.key,
+            accounts.rewards.key,
+            amount,
+        ),
+        None => instruction::create_stake(
+            program.key,
+            accounts.owner.key,
+            accounts.owner_token.key,
+            accounts.stake.key,
+            accounts.rewards.key,
+            amount,
+        ),
+    };
+    let mut account_infos = vec![
+        accounts.owner,
+        accounts.owner_token,
+        accounts.stake,
+        accounts.pda_token,
+        accounts.token_program,
+        accounts.rewards,
+        program,
+    ];
+    account_infos.extend(accounts.stake_owner);
+    invoke_signed(&ix, &account_infos, signer_seeds)
+}
+
+pub fn end_stake<'a>(
+    program: AccountInfo<'a>,
+    accounts: EndStakeAccounts<'a>,
+    signer_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    let ix = instruction::end_stake(
+        program.key,
+        accounts.owner.key,
+        accounts.owner_token.key,
+        accounts.stake.key,
+        accounts.rewards.key,
+    );
+    invoke_signed(&ix, &accounts.into_infos(program), signer_seeds)
+}
+
+pub fn emergency_withdraw<'a>(
+    program: AccountInfo<'a>,
+    accounts: EndStakeAccounts<'a>,
+    signer_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    let ix = instruction::emergency_withdraw(
+        program.key,
+        accounts.owner.key,
+        accounts.owner_token.key,
+        accounts.stake.key,
+        accounts.rewards.key,
+    );
+    invoke_signed(&ix, &accounts.into_infos(program), signer_seeds)
+}
+
+/// Donates to an initialized pool.
+pub fn add_to_rewards_pool<'a>(
+    program: AccountInfo<'a>,
+    accounts: AddToRewardsPoolAccounts<'a>,
+    amount: u64,
+    airdrop: bool,
+    signer_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    let ix = instruction::add_to_rewards_pool(
+        program.key,
+        accounts.donator.key,
+        accounts.donator_token.key,
+        accounts.rewards.key,
+        amount,
+        airdrop,
+    );
+    invoke_signed(
+        &ix,
+        &[
+            accounts.donator,
+            accounts.donator_token,
+            accounts.pda_token,
+            accounts.token_program,
+            accounts.rewards,
+            accounts.authority,
+            program,
+        ],
+        signer_seeds,
+    )
+}
+
+pub fn vote<'a>(
+    program: AccountInfo<'a>,
+    accounts: VoteAccounts<'a>,
+    selection: u8,
+    signer_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    let ix = instruction::vote(
+        program.key,
+        accounts.owner.key,
+        accounts.owner_token.key,
+        accounts.ballot.key,
+        accounts.rewards.key,
+        selection,
+    );
+    invoke_signed(
+        &ix,
+        &[
+            accounts.owner,
+            accounts.owner_token,
+            accounts.ballot,
+            accounts.pda_token,
+            accounts.token_program,
+            accounts.rewards,
+            accounts.authority,
+            program,
+        ],
+        signer_seeds,
+    )
+}
+
+pub fn airdrop<'a>(
+    program: AccountInfo<'a>,
+    accounts: AirdropAccounts<'a>,
+    amount: u64,
+    signer_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    let ix = instruction::airdrop(
+        program.key,
+        accounts.owner.key,
+        accounts.owner_token.key,
+        accounts.rewards.key,
+        accounts.treasury.key,
+        accounts.owner_fee_token.as_ref().map(|account| account.key),
+        amount,
+    );
+    let mut account_infos = vec![
+        accounts.owner,
+        accounts.owner_token,
+        accounts.pda,
+        accounts.pda_token,
+        accounts.token_program,
+        accounts.rewards,
+        accounts.treasury,
+        accounts.airdrop_receipt,
+        accounts.system_program,
+        accounts.vesting_schedule,
+        program,
+    ];
+    account_infos.extend(accounts.owner_fee_token);
+    invoke_signed(&ix, &account_infos, signer_seeds)
+}
+
+pub fn release_vested<'a>(
+    program: AccountInfo<'a>,
+    accounts: ReleaseVestedAccounts<'a>,
+    signer_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    let ix = instruction::release_vested(
+        program.key,
+        accounts.beneficiary.key,
+        accounts.beneficiary_token.key,
+        accounts.rewards.key,
+    );
+    invoke_signed(
+        &ix,
+        &[
+            accounts.beneficiary,
+            accounts.beneficiary_token,
+            accounts.vesting_schedule,
+            accounts.pda,
+            accounts.pda_token,
+            accounts.token_program,
+            accounts.rewards,
+            program,
+        ],
+        signer_seeds,
+    )
+}
+
+/// Withdraws what has vested from the beneficiary's contract number `id`.
+pub fn withdraw<'a>(
+    program: AccountInfo<'a>,
+    accounts: WithdrawAccounts<'a>,
+    id: u64,
+    signer_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    let ix = instruction::withdraw(
+        program.key,
+        accounts.beneficiary.key,
+        accounts.beneficiary_token.key,
+        id,
+    );
+    invoke_signed(
+        &ix,
+        &[
+            accounts.beneficiary,
+            accounts.beneficiary_token,
+            accounts.vesting_contract,
+            accounts.vesting_vault,
+            accounts.token_program,
+            program,
+        ],
+        signer_seeds,
+    )
+}
+
+/// Leaves a `reward::RewardSimulation` in the return data, see `instruction::simulate_reward`.
+pub fn simulate_reward<'a>(
+    program: AccountInfo<'a>,
+    rewards: AccountInfo<'a>,
+    stake: Option<AccountInfo<'a>>,
+    amount: u64,
+    duration: i64,
+) -> ProgramResult {
+    let ix = instruction::simulate_reward(
+        program.key,
+        rewards.key,
+        stake.as_ref().map(|account| account.key),
+        amount,
+        duration,
+    );
+    let mut account_infos = vec![rewards, program];
+    account_infos.extend(stake);
+    invoke_signed(&ix, &account_infos, &[])
+}
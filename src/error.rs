@@ -0,0 +1,23 @@
+//! Errors specific to the staking program, surfaced as `ProgramError::Custom`.
+//! Variants are only ever appended, so an error's code never changes meaning.
+use solana_program::program_error::ProgramError;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StakingError {
+    MathOverflow, //An amount, counter or timestamp left its type's range.
+}
+
+impl fmt::Display for StakingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StakingError::MathOverflow => write!(f, "Math overflow"),
+        }
+    }
+}
+
+impl From<StakingError> for ProgramError {
+    fn from(e: StakingError) -> Self {
+        ProgramError::Custom(e as u32)
+    }
+}
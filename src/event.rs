@@ -0,0 +1,183 @@
+//! Structured logs for every state change.
+//!
+//! Events are Borsh encoded and written with `sol_log_data`, which the runtime logs as
+//! `Program data: <base64> <base64>`. The first field is `EVENT_TAG`, the second the event.
+//! Variants are only ever appended, so an event's tag byte and fields never change meaning.
+use crate::state::{AuthorityType, PoolBucket};
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{entrypoint::ProgramResult, log::sol_log_data, pubkey::Pubkey};
+
+/// Tells our events apart from data logged by other programs in the same transaction.
+pub const EVENT_TAG: &[u8] = b"kc:event";
+
+const LOG_PREFIX: &str = "Program data: ";
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub enum Event {
+    StakeCreated {
+        owner: Pubkey,
+        stake: Pubkey,
+        amount: u64,
+        time_started: i64,
+    },
+    StakeEnded {
+        owner: Pubkey,
+        stake: Pubkey,
+        principal: u64,
+        reward: u64,
+    },
+    EmergencyWithdrawn {
+        owner: Pubkey,
+        stake: Pubkey,
+        amount: u64,
+    },
+    RewardsAdded {
+        donator: Pubkey,
+        amount: u64,
+        bucket: PoolBucket,
+    },
+    Voted {
+        voter: Pubkey,
+        ballot: Pubkey,
+        selection: u8,
+        fee: u64,
+    },
+    Airdropped {
+        owner: Pubkey,
+        amount: u64,
+        fee: u64,
+        vesting: bool, //Coins went to a vesting schedule instead of the owner.
+    },
+    VestedReleased {
+        beneficiary: Pubkey,
+        schedule: Pubkey,
+        amount: u64,
+    },
+    FeeChanged {
+        fee: u64,
+    },
+    AirdropLimitsChanged {
+        wallet_cap: u64,
+        cooldown: i64,
+    },
+    AirdropTreasuryChanged {
+        fee_mint: Pubkey,
+        treasury: Pubkey,
+    },
+    AirdropVestingChanged {
+        cliff: i64,
+        duration: i64,
+    },
+    VestingCreated {
+        beneficiary: Pubkey,
+        contract: Pubkey,
+        id: u64,
+        amount: u64,
+        start: i64,
+        cliff: i64,
+        end: i64,
+        revocable: bool,
+    },
+    VestingWithdrawn {
+        beneficiary: Pubkey,
+        contract: Pubkey,
+        amount: u64,
+    },
+    VestingRevoked {
+        contract: Pubkey,
+        returned: u64,
+    },
+    AuthorityInitialized {
+        admin: Pubkey,
+    },
+    AuthorityChanged {
+        authority_type: AuthorityType,
+        new_authority: Pubkey,
+    },
+    AdminProposed {
+        new_admin: Pubkey,
+    },
+    AdminAccepted {
+        new_admin: Pubkey,
+    },
+    AdminRenounced,
+    MultisigInitialized {
+        multisig: Pubkey,
+        m: u8,
+        n: u8,
+    },
+    PauseChanged {
+        paused: u8,
+    },
+    PoolWithdrawn {
+        bucket: PoolBucket,
+        amount: u64,
+    },
+    Rebalanced {
+        from: PoolBucket,
+        to: PoolBucket,
+        amount: u64,
+    },
+    AccountMigrated {
+        legacy: Pubkey,
+        new: Pubkey,
+    },
+    //An emergency withdrawal refunded the stake without touching the pool, whose header or
+    //counters were inconsistent.
+    PoolAccountingSkipped {
+        rewards: Pubkey,
+        stake: Pubkey,
+    },
+}
+
+impl Event {
+    pub fn emit(&self) -> ProgramResult {
+        sol_log_data(&[EVENT_TAG, &self.try_to_vec()?]);
+        Ok(())
+    }
+
+    /// Decodes the fields of one `sol_log_data` call, `None` when they are not one of our events.
+    pub fn decode(fields: &[&[u8]]) -> Option<Event> {
+        match fields {
+            [tag, data] if *tag == EVENT_TAG => Event::try_from_slice(data).ok(),
+            _ => None,
+        }
+    }
+
+    /// Decodes a `Program data:` log line.
+    pub fn from_log(line: &str) -> Option<Event> {
+        let fields = line
+            .strip_prefix(LOG_PREFIX)?
+            .split_whitespace()
+            .map(base64::decode)
+            .collect::<Result<Vec<_>, _>>()
+            .ok()?;
+        let fields: Vec<&[u8]> = fields.iter().map(Vec::as_slice).collect();
+        Event::decode(&fields)
+    }
+
+    /// Events `program_id` emitted in a transaction's log messages, in order. Invocations are
+    /// tracked so other programs cannot log events in our name.
+    pub fn from_logs<S: AsRef<str>>(program_id: &Pubkey, logs: &[S]) -> Vec<Event> {
+        let program_id = program_id.to_string();
+        let mut invocations = Vec::new();
+        let mut events = Vec::new();
+        for line in logs {
+            let line = line.as_ref();
+            let mut words = line.split_whitespace();
+            match (words.next(), words.next(), words.next()) {
+                (Some("Program"), Some(id), Some("invoke")) => invocations.push(id),
+                (Some("Program"), Some(_), Some("success"))
+                | (Some("Program"), Some(_), Some("failed:")) => {
+                    invocations.pop();
+                }
+                _ => {
+                    if invocations.last() == Some(&program_id.as_str()) {
+                        events.extend(Event::from_log(line));
+                    }
+                }
+            }
+        }
+        events
+    }
+}
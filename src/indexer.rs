@@ -0,0 +1,115 @@
+//! Off-chain decoding of program accounts and transaction logs, built with the `indexer` feature.
+use crate::event::Event;
+use crate::math::CheckedMath;
+use crate::state::{
+    AccountHeader, AccountState, AirdropReceipt, Authority, Ballot, Multisig, RewardsPool, Stake,
+    VestingContract, VestingSchedule,
+};
+use borsh::BorshDeserialize;
+use solana_program::{program_error::ProgramError, pubkey::Pubkey};
+
+pub use crate::reward::pending_reward;
+
+/// Any account owned by the program, told apart by its header.
+#[derive(Debug)]
+pub enum ProgramAccount {
+    Stake(Stake),
+    RewardsPool(RewardsPool),
+    Ballot(Ballot),
+    AirdropReceipt(AirdropReceipt),
+    VestingSchedule(VestingSchedule),
+    VestingContract(VestingContract),
+    Authority(Authority),
+    Multisig(Multisig),
+}
+
+impl ProgramAccount {
+    /// Decodes raw account data. Closed and legacy accounts are `InvalidAccountData`, run
+    /// `MigrateAccount` on the latter.
+    pub fn decode(data: &[u8]) -> Result<ProgramAccount, ProgramError> {
+        let header = AccountHeader::deserialize(&mut &data[..])?;
+        match header.discriminator {
+            Stake::DISCRIMINATOR => decode::<Stake>(data).map(ProgramAccount::Stake),
+            RewardsPool::DISCRIMINATOR => {
+                decode::<RewardsPool>(data).map(ProgramAccount::RewardsPool)
+            }
+            Ballot::DISCRIMINATOR => decode::<Ballot>(data).map(ProgramAccount::Ballot),
+            AirdropReceipt::DISCRIMINATOR => {
+                decode::<AirdropReceipt>(data).map(ProgramAccount::AirdropReceipt)
+            }
+            VestingSchedule::DISCRIMINATOR => {
+                decode::<VestingSchedule>(data).map(ProgramAccount::VestingSchedule)
+            }
+            VestingContract::DISCRIMINATOR => {
+                decode::<VestingContract>(data).map(ProgramAccount::VestingContract)
+            }
+            Authority::DISCRIMINATOR => decode::<Authority>(data).map(ProgramAccount::Authority),
+            Multisig::DISCRIMINATOR => decode::<Multisig>(data).map(ProgramAccount::Multisig),
+            _ => Err(ProgramError::InvalidAccountData),
+        }
+    }
+}
+
+/// Decodes an account of a known type, checking its header like the program does.
+pub fn decode<T: AccountState>(data: &[u8]) -> Result<T, ProgramError> {
+    let header = AccountHeader::deserialize(&mut &data[..])?;
+    if header != T::header() {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(T::try_from_slice(data)?)
+}
+
+/// Events the program emitted, from a transaction's log messages.
+pub fn decode_events<S: AsRef<str>>(program_id: &Pubkey, logs: &[S]) -> Vec<Event> {
+    Event::from_logs(program_id, logs)
+}
+
+/// Coins locked in the program, summed over a set of decoded accounts.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Tvl {
+    pub staked: u64, //Principal of the stake accounts.
+    pub stakes_count: u32,
+    pub rewards: u64,
+    pub airdrop: u64,
+    pub vesting_locked: u64,    //Airdrops vesting in the vault.
+    pub vesting_contracts: u64, //Unreleased coins in team vesting vaults.
+}
+
+impl Tvl {
+    /// Every coin the program holds. Fails with `MathOverflow` for accounts no real pool could
+    /// hold together.
+    pub fn total(&self) -> Result<u64, ProgramError> {
+        self.staked
+            .try_add(self.rewards)?
+            .try_add(self.airdrop)?
+            .try_add(self.vesting_locked)?
+            .try_add(self.vesting_contracts)
+    }
+}
+
+/// Sums the locked coins, failing with `MathOverflow` on totals past `u64::MAX` or on a vesting
+/// contract that released more than it holds.
+pub fn tvl<'a, I: IntoIterator<Item = &'a ProgramAccount>>(
+    accounts: I,
+) -> Result<Tvl, ProgramError> {
+    let mut tvl = Tvl::default();
+    for account in accounts {
+        match account {
+            ProgramAccount::Stake(stake) if stake.is_initialized => {
+                tvl.staked = tvl.staked.try_add(stake.amount)?;
+                tvl.stakes_count = tvl.stakes_count.try_add(1)?;
+            }
+            ProgramAccount::RewardsPool(pool) if pool.is_initialized => {
+                tvl.rewards = tvl.rewards.try_add(pool.amount)?;
+                tvl.airdrop = tvl.airdrop.try_add(pool.airdrop_supply)?;
+                tvl.vesting_locked = tvl.vesting_locked.try_add(pool.vesting_locked)?;
+            }
+            ProgramAccount::VestingContract(contract) if contract.is_initialized => {
+                let unreleased = contract.total.try_sub(contract.released)?;
+                tvl.vesting_contracts = tvl.vesting_contracts.try_add(unreleased)?;
+            }
+            _ => {}
+        }
+    }
+    Ok(tvl)
+}
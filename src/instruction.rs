@@ -1,4 +1,16 @@
-use solana_program::{program_error::ProgramError};
+use solana_program::{
+    instruction::{AccountMeta, Instruction as SolInstruction},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    system_program,
+};
+
+use crate::processor::{ADMIN_ADDRESS, KITTYCOIN_MINT};
+use crate::state::{AuthorityType, PoolBucket};
+use crate::{
+    get_airdrop_receipt_address, get_airdrop_vesting_address, get_authority_address,
+    get_pda_address, get_vesting_contract_address,
+};
 
 pub enum Instruction {
     CreateStake {amount: u64},
@@ -7,6 +19,25 @@ pub enum Instruction {
     Vote {selection: u8},
     Airdrop {amount: u64},
     ChangeAirdropFee {fee: u64},
+    SetAirdropLimits {wallet_cap: u64, cooldown: i64},
+    SetAirdropTreasury {fee_mint: Pubkey, treasury: Pubkey},
+    SetAirdropVesting {cliff: i64, duration: i64},
+    ReleaseVested,
+    CreateVesting {id: u64, start: i64, cliff: i64, end: i64, amount: u64, revocable: bool},
+    Withdraw,
+    RevokeVesting,
+    InitializeAuthority,
+    SetAuthority {authority_type: AuthorityType, new_authority: Pubkey},
+    ProposeAdmin {new_admin: Pubkey},
+    AcceptAdmin,
+    RenounceAdmin,
+    InitializeMultisig {m: u8},
+    SetPause {paused: u8},
+    EmergencyWithdraw,
+    WithdrawFromPool {bucket: PoolBucket, amount: u64},
+    Rebalance {from: PoolBucket, to: PoolBucket, amount: u64},
+    MigrateAccount,
+    SimulateReward {amount: u64, duration: i64},
 }
 
 impl Instruction {
@@ -37,14 +68,199 @@ impl Instruction {
                 let (fee, _rest) = Self::unpack_u64(rest)?;
                 Self::ChangeAirdropFee {fee}
             }
+            6 => {
+                let (wallet_cap, rest) = Self::unpack_u64(rest)?;
+                let (cooldown, _rest) = Self::unpack_i64(rest)?;
+                Self::SetAirdropLimits {wallet_cap, cooldown}
+            }
+            7 => {
+                let (fee_mint, rest) = Self::unpack_pubkey(rest)?;
+                let (treasury, _rest) = Self::unpack_pubkey(rest)?;
+                Self::SetAirdropTreasury {fee_mint, treasury}
+            }
+            8 => {
+                let (cliff, rest) = Self::unpack_i64(rest)?;
+                let (duration, _rest) = Self::unpack_i64(rest)?;
+                Self::SetAirdropVesting {cliff, duration}
+            }
+            9 => {
+                Self::ReleaseVested
+            }
+            10 => {
+                let (id, rest) = Self::unpack_u64(rest)?;
+                let (start, rest) = Self::unpack_i64(rest)?;
+                let (cliff, rest) = Self::unpack_i64(rest)?;
+                let (end, rest) = Self::unpack_i64(rest)?;
+                let (amount, rest) = Self::unpack_u64(rest)?;
+                let (revocable, _rest) = Self::unpack_bool(rest)?;
+                Self::CreateVesting {id, start, cliff, end, amount, revocable}
+            }
+            11 => {
+                Self::Withdraw
+            }
+            12 => {
+                Self::RevokeVesting
+            }
+            13 => {
+                Self::InitializeAuthority
+            }
+            14 => {
+                let (authority_type, rest) = Self::unpack_authority_type(rest)?;
+                let (new_authority, _rest) = Self::unpack_pubkey(rest)?;
+                Self::SetAuthority {authority_type, new_authority}
+            }
+            15 => {
+                let (new_admin, _rest) = Self::unpack_pubkey(rest)?;
+                Self::ProposeAdmin {new_admin}
+            }
+            16 => {
+                Self::AcceptAdmin
+            }
+            17 => {
+                Self::RenounceAdmin
+            }
+            18 => {
+                let (m, _rest) = Self::unpack_u8(rest)?;
+                Self::InitializeMultisig {m}
+            }
+            19 => {
+                let (paused, _rest) = Self::unpack_u8(rest)?;
+                Self::SetPause {paused}
+            }
+            20 => {
+                Self::EmergencyWithdraw
+            }
+            21 => {
+                let (bucket, rest) = Self::unpack_bucket(rest)?;
+                let (amount, _rest) = Self::unpack_u64(rest)?;
+                Self::WithdrawFromPool {bucket, amount}
+            }
+            22 => {
+                let (from, rest) = Self::unpack_bucket(rest)?;
+                let (to, rest) = Self::unpack_bucket(rest)?;
+                let (amount, _rest) = Self::unpack_u64(rest)?;
+                Self::Rebalance {from, to, amount}
+            }
+            23 => {
+                Self::MigrateAccount
+            }
+            24 => {
+                let (amount, rest) = Self::unpack_u64(rest)?;
+                let (duration, _rest) = Self::unpack_i64(rest)?;
+                Self::SimulateReward {amount, duration}
+            }
             _ => {
                 return Err(ProgramError::InvalidInstructionData);
             }
         })
     }
 
+    pub fn pack(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        match self {
+            Self::CreateStake { amount } => {
+                buf.push(0);
+                buf.extend_from_slice(&amount.to_le_bytes());
+            }
+            Self::EndStake => buf.push(1),
+            Self::AddToRewardsPool { amount, airdrop } => {
+                buf.push(2);
+                buf.extend_from_slice(&amount.to_le_bytes());
+                buf.push(*airdrop as u8);
+            }
+            Self::Vote { selection } => {
+                buf.push(3);
+                buf.push(*selection);
+            }
+            Self::Airdrop { amount } => {
+                buf.push(4);
+                buf.extend_from_slice(&amount.to_le_bytes());
+            }
+            Self::ChangeAirdropFee { fee } => {
+                buf.push(5);
+                buf.extend_from_slice(&fee.to_le_bytes());
+            }
+            Self::SetAirdropLimits { wallet_cap, cooldown } => {
+                buf.push(6);
+                buf.extend_from_slice(&wallet_cap.to_le_bytes());
+                buf.extend_from_slice(&cooldown.to_le_bytes());
+            }
+            Self::SetAirdropTreasury { fee_mint, treasury } => {
+                buf.push(7);
+                buf.extend_from_slice(fee_mint.as_ref());
+                buf.extend_from_slice(treasury.as_ref());
+            }
+            Self::SetAirdropVesting { cliff, duration } => {
+                buf.push(8);
+                buf.extend_from_slice(&cliff.to_le_bytes());
+                buf.extend_from_slice(&duration.to_le_bytes());
+            }
+            Self::ReleaseVested => buf.push(9),
+            Self::CreateVesting {
+                id,
+                start,
+                cliff,
+                end,
+                amount,
+                revocable,
+            } => {
+                buf.push(10);
+                buf.extend_from_slice(&id.to_le_bytes());
+                buf.extend_from_slice(&start.to_le_bytes());
+                buf.extend_from_slice(&cliff.to_le_bytes());
+                buf.extend_from_slice(&end.to_le_bytes());
+                buf.extend_from_slice(&amount.to_le_bytes());
+                buf.push(*revocable as u8);
+            }
+            Self::Withdraw => buf.push(11),
+            Self::RevokeVesting => buf.push(12),
+            Self::InitializeAuthority => buf.push(13),
+            Self::SetAuthority {
+                authority_type,
+                new_authority,
+            } => {
+                buf.push(14);
+                buf.push(*authority_type as u8);
+                buf.extend_from_slice(new_authority.as_ref());
+            }
+            Self::ProposeAdmin { new_admin } => {
+                buf.push(15);
+                buf.extend_from_slice(new_admin.as_ref());
+            }
+            Self::AcceptAdmin => buf.push(16),
+            Self::RenounceAdmin => buf.push(17),
+            Self::InitializeMultisig { m } => {
+                buf.push(18);
+                buf.push(*m);
+            }
+            Self::SetPause { paused } => {
+                buf.push(19);
+                buf.push(*paused);
+            }
+            Self::EmergencyWithdraw => buf.push(20),
+            Self::WithdrawFromPool { bucket, amount } => {
+                buf.push(21);
+                buf.push(*bucket as u8);
+                buf.extend_from_slice(&amount.to_le_bytes());
+            }
+            Self::Rebalance { from, to, amount } => {
+                buf.push(22);
+                buf.push(*from as u8);
+                buf.push(*to as u8);
+                buf.extend_from_slice(&amount.to_le_bytes());
+            }
+            Self::MigrateAccount => buf.push(23),
+            Self::SimulateReward { amount, duration } => {
+                buf.push(24);
+                buf.extend_from_slice(&amount.to_le_bytes());
+                buf.extend_from_slice(&duration.to_le_bytes());
+            }
+        }
+        buf
+    }
+
     fn unpack_u8(input: &[u8]) -> Result<(u8, &[u8]), ProgramError> {
-        if input.len() >= 1 {
+        if !input.is_empty() {
             let (uint, rest) = input.split_at(1);
             Ok((uint[0], rest))
         } else {
@@ -89,6 +305,11 @@ impl Instruction {
         }
     }
 
+    fn unpack_i64(input: &[u8]) -> Result<(i64, &[u8]), ProgramError> {
+        let (uint, rest) = Self::unpack_u64(input)?;
+        Ok((uint as i64, rest))
+    }
+
     // fn unpack_u128(input: &[u8]) -> Result<(u128, &[u8]), ProgramError> {
     //     if input.len() >= 16 {
     //         let (uint, rest) = input.split_at(16);
@@ -105,7 +326,7 @@ impl Instruction {
     // }
 
     fn unpack_bool(input: &[u8]) -> Result<(bool, &[u8]), ProgramError> {
-        if input.len() >= 1 {
+        if !input.is_empty() {
             let (uint, rest) = input.split_at(1);
             Ok((uint[0] == 1, rest))
         } else {
@@ -113,13 +334,676 @@ impl Instruction {
         }
     }
 
-    // fn unpack_pubkey(input: &[u8]) -> Result<(Pubkey, &[u8]), ProgramError> {
-    //     if input.len() >= 32 {
-    //         let (key, rest) = input.split_at(32);
-    //         let pk = Pubkey::new(key);
-    //         Ok((pk, rest))
-    //     } else {
-    //         Err(ProgramError::InvalidInstructionData)
-    //     }
-    // }
+    fn unpack_authority_type(input: &[u8]) -> Result<(AuthorityType, &[u8]), ProgramError> {
+        let (authority_type, rest) = Self::unpack_u8(input)?;
+        let authority_type = match authority_type {
+            0 => AuthorityType::PoolAdmin,
+            1 => AuthorityType::FeeManager,
+            2 => AuthorityType::BallotCreator,
+            3 => AuthorityType::Treasury,
+            4 => AuthorityType::Guardian,
+            _ => return Err(ProgramError::InvalidInstructionData),
+        };
+        Ok((authority_type, rest))
+    }
+
+    fn unpack_bucket(input: &[u8]) -> Result<(PoolBucket, &[u8]), ProgramError> {
+        let (bucket, rest) = Self::unpack_u8(input)?;
+        let bucket = match bucket {
+            0 => PoolBucket::Rewards,
+            1 => PoolBucket::Airdrop,
+            _ => return Err(ProgramError::InvalidInstructionData),
+        };
+        Ok((bucket, rest))
+    }
+
+    fn unpack_pubkey(input: &[u8]) -> Result<(Pubkey, &[u8]), ProgramError> {
+        if input.len() >= 32 {
+            let (key, rest) = input.split_at(32);
+            let pk = Pubkey::new(key);
+            Ok((pk, rest))
+        } else {
+            Err(ProgramError::InvalidInstructionData)
+        }
+    }
+}
+
+/// Puts the authority first, as a signer itself or as a multisig followed by its `signers`.
+fn authority_accounts(
+    authority: &Pubkey,
+    signers: &[&Pubkey],
+    accounts: Vec<AccountMeta>,
+) -> Vec<AccountMeta> {
+    let mut all_accounts = vec![AccountMeta::new_readonly(*authority, signers.is_empty())];
+    all_accounts.extend(accounts);
+    for signer in signers {
+        all_accounts.push(AccountMeta::new_readonly(**signer, true));
+    }
+    all_accounts
+}
+
+fn pda_token_address(program_id: &Pubkey) -> Pubkey {
+    spl_associated_token_account::get_associated_token_address(
+        &get_pda_address(program_id),
+        &Pubkey::new_from_array(KITTYCOIN_MINT),
+    )
+}
+
+pub fn create_stake(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    owner_token: &Pubkey,
+    stake: &Pubkey,
+    rewards: &Pubkey,
+    amount: u64,
+) -> SolInstruction {
+    SolInstruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*owner, true),
+            AccountMeta::new(*owner_token, false),
+            AccountMeta::new(*stake, false),
+            AccountMeta::new(pda_token_address(program_id), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new(*rewards, false),
+        ],
+        data: Instruction::CreateStake { amount }.pack(),
+    }
+}
+
+/// Like `create_stake`, but the stake belongs to `owner` while `authority` pays for it. Both
+/// sign, so a program can own stakes funded by its users by signing for its PDA.
+pub fn create_stake_for(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    authority_token: &Pubkey,
+    owner: &Pubkey,
+    stake: &Pubkey,
+    rewards: &Pubkey,
+    amount: u64,
+) -> SolInstruction {
+    let mut instruction = create_stake(
+        program_id,
+        authority,
+        authority_token,
+        stake,
+        rewards,
+        amount,
+    );
+    instruction
+        .accounts
+        .push(AccountMeta::new_readonly(*owner, true));
+    instruction
+}
+
+pub fn end_stake(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    owner_token: &Pubkey,
+    stake: &Pubkey,
+    rewards: &Pubkey,
+) -> SolInstruction {
+    SolInstruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*owner, true),
+            AccountMeta::new(*owner_token, false),
+            AccountMeta::new(*stake, false),
+            AccountMeta::new_readonly(get_pda_address(program_id), false),
+            AccountMeta::new(pda_token_address(program_id), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new(*rewards, false),
+        ],
+        data: Instruction::EndStake.pack(),
+    }
+}
+
+/// Same accounts as `end_stake`, returns the principal only.
+pub fn emergency_withdraw(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    owner_token: &Pubkey,
+    stake: &Pubkey,
+    rewards: &Pubkey,
+) -> SolInstruction {
+    SolInstruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*owner, true),
+            AccountMeta::new(*owner_token, false),
+            AccountMeta::new(*stake, false),
+            AccountMeta::new_readonly(get_pda_address(program_id), false),
+            AccountMeta::new(pda_token_address(program_id), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new(*rewards, false),
+        ],
+        data: Instruction::EmergencyWithdraw.pack(),
+    }
+}
+
+pub fn add_to_rewards_pool(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    admin_token: &Pubkey,
+    rewards: &Pubkey,
+    amount: u64,
+    airdrop: bool,
+) -> SolInstruction {
+    SolInstruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*admin, true),
+            AccountMeta::new(*admin_token, false),
+            AccountMeta::new(pda_token_address(program_id), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new(*rewards, false),
+            AccountMeta::new_readonly(get_authority_address(program_id), false),
+        ],
+        data: Instruction::AddToRewardsPool { amount, airdrop }.pack(),
+    }
+}
+
+pub fn vote(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    owner_token: &Pubkey,
+    ballot: &Pubkey,
+    rewards: &Pubkey,
+    selection: u8,
+) -> SolInstruction {
+    SolInstruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*owner, true),
+            AccountMeta::new(*owner_token, false),
+            AccountMeta::new(*ballot, false),
+            AccountMeta::new(pda_token_address(program_id), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new(*rewards, false),
+            AccountMeta::new_readonly(get_authority_address(program_id), false),
+        ],
+        data: Instruction::Vote { selection }.pack(),
+    }
+}
+
+/// `owner_fee_token` is required when the pool charges its fee in an SPL token.
+pub fn airdrop(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    owner_token: &Pubkey,
+    rewards: &Pubkey,
+    treasury: &Pubkey,
+    owner_fee_token: Option<&Pubkey>,
+    amount: u64,
+) -> SolInstruction {
+    let mut accounts = vec![
+        AccountMeta::new(*owner, true),
+        AccountMeta::new(*owner_token, false),
+        AccountMeta::new_readonly(get_pda_address(program_id), false),
+        AccountMeta::new(pda_token_address(program_id), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new(*rewards, false),
+        AccountMeta::new(*treasury, false),
+        AccountMeta::new(get_airdrop_receipt_address(program_id, owner), false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new(get_airdrop_vesting_address(program_id, owner), false),
+    ];
+    if let Some(owner_fee_token) = owner_fee_token {
+        accounts.push(AccountMeta::new(*owner_fee_token, false));
+    }
+    SolInstruction {
+        program_id: *program_id,
+        accounts,
+        data: Instruction::Airdrop { amount }.pack(),
+    }
+}
+
+pub fn change_airdrop_fee(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    signers: &[&Pubkey],
+    rewards: &Pubkey,
+    fee: u64,
+) -> SolInstruction {
+    SolInstruction {
+        program_id: *program_id,
+        accounts: authority_accounts(
+            admin,
+            signers,
+            vec![
+                AccountMeta::new(*rewards, false),
+                AccountMeta::new_readonly(get_authority_address(program_id), false),
+            ],
+        ),
+        data: Instruction::ChangeAirdropFee { fee }.pack(),
+    }
+}
+
+pub fn set_airdrop_limits(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    signers: &[&Pubkey],
+    rewards: &Pubkey,
+    wallet_cap: u64,
+    cooldown: i64,
+) -> SolInstruction {
+    SolInstruction {
+        program_id: *program_id,
+        accounts: authority_accounts(
+            admin,
+            signers,
+            vec![
+                AccountMeta::new(*rewards, false),
+                AccountMeta::new_readonly(get_authority_address(program_id), false),
+            ],
+        ),
+        data: Instruction::SetAirdropLimits {
+            wallet_cap,
+            cooldown,
+        }
+        .pack(),
+    }
+}
+
+pub fn set_airdrop_treasury(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    signers: &[&Pubkey],
+    rewards: &Pubkey,
+    fee_mint: &Pubkey,
+    treasury: &Pubkey,
+) -> SolInstruction {
+    SolInstruction {
+        program_id: *program_id,
+        accounts: authority_accounts(
+            admin,
+            signers,
+            vec![
+                AccountMeta::new(*rewards, false),
+                AccountMeta::new_readonly(get_authority_address(program_id), false),
+            ],
+        ),
+        data: Instruction::SetAirdropTreasury {
+            fee_mint: *fee_mint,
+            treasury: *treasury,
+        }
+        .pack(),
+    }
+}
+
+pub fn set_airdrop_vesting(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    signers: &[&Pubkey],
+    rewards: &Pubkey,
+    cliff: i64,
+    duration: i64,
+) -> SolInstruction {
+    SolInstruction {
+        program_id: *program_id,
+        accounts: authority_accounts(
+            admin,
+            signers,
+            vec![
+                AccountMeta::new(*rewards, false),
+                AccountMeta::new_readonly(get_authority_address(program_id), false),
+            ],
+        ),
+        data: Instruction::SetAirdropVesting { cliff, duration }.pack(),
+    }
+}
+
+pub fn release_vested(
+    program_id: &Pubkey,
+    beneficiary: &Pubkey,
+    beneficiary_token: &Pubkey,
+    rewards: &Pubkey,
+) -> SolInstruction {
+    SolInstruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*beneficiary, true),
+            AccountMeta::new(*beneficiary_token, false),
+            AccountMeta::new(get_airdrop_vesting_address(program_id, beneficiary), false),
+            AccountMeta::new_readonly(get_pda_address(program_id), false),
+            AccountMeta::new(pda_token_address(program_id), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new(*rewards, false),
+        ],
+        data: Instruction::ReleaseVested.pack(),
+    }
+}
+
+fn vesting_vault_address(vesting: &Pubkey) -> Pubkey {
+    spl_associated_token_account::get_associated_token_address(
+        vesting,
+        &Pubkey::new_from_array(KITTYCOIN_MINT),
+    )
+}
+
+/// The vault is the contract's associated token account and must exist before
+/// the contract is created.
+#[allow(clippy::too_many_arguments)]
+pub fn create_vesting(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    admin_token: &Pubkey,
+    beneficiary: &Pubkey,
+    id: u64,
+    start: i64,
+    cliff: i64,
+    end: i64,
+    amount: u64,
+    revocable: bool,
+) -> SolInstruction {
+    let vesting = get_vesting_contract_address(program_id, beneficiary, id);
+    SolInstruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*admin, true),
+            AccountMeta::new(*admin_token, false),
+            AccountMeta::new_readonly(*beneficiary, false),
+            AccountMeta::new(vesting, false),
+            AccountMeta::new(vesting_vault_address(&vesting), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(get_authority_address(program_id), false),
+        ],
+        data: Instruction::CreateVesting {
+            id,
+            start,
+            cliff,
+            end,
+            amount,
+            revocable,
+        }
+        .pack(),
+    }
+}
+
+pub fn withdraw(
+    program_id: &Pubkey,
+    beneficiary: &Pubkey,
+    beneficiary_token: &Pubkey,
+    id: u64,
+) -> SolInstruction {
+    let vesting = get_vesting_contract_address(program_id, beneficiary, id);
+    SolInstruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*beneficiary, true),
+            AccountMeta::new(*beneficiary_token, false),
+            AccountMeta::new(vesting, false),
+            AccountMeta::new(vesting_vault_address(&vesting), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: Instruction::Withdraw.pack(),
+    }
+}
+
+pub fn revoke_vesting(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    signers: &[&Pubkey],
+    admin_token: &Pubkey,
+    beneficiary: &Pubkey,
+    id: u64,
+) -> SolInstruction {
+    let vesting = get_vesting_contract_address(program_id, beneficiary, id);
+    SolInstruction {
+        program_id: *program_id,
+        accounts: authority_accounts(
+            admin,
+            signers,
+            vec![
+                AccountMeta::new(*admin_token, false),
+                AccountMeta::new(vesting, false),
+                AccountMeta::new(vesting_vault_address(&vesting), false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+                AccountMeta::new_readonly(get_authority_address(program_id), false),
+            ],
+        ),
+        data: Instruction::RevokeVesting.pack(),
+    }
+}
+
+/// Must be signed by the deploy key, which receives every role.
+pub fn initialize_authority(program_id: &Pubkey) -> SolInstruction {
+    SolInstruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(Pubkey::new_from_array(ADMIN_ADDRESS), true),
+            AccountMeta::new(get_authority_address(program_id), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        data: Instruction::InitializeAuthority.pack(),
+    }
+}
+
+pub fn set_authority(
+    program_id: &Pubkey,
+    current_authority: &Pubkey,
+    signers: &[&Pubkey],
+    authority_type: AuthorityType,
+    new_authority: &Pubkey,
+) -> SolInstruction {
+    SolInstruction {
+        program_id: *program_id,
+        accounts: authority_accounts(
+            current_authority,
+            signers,
+            vec![AccountMeta::new(get_authority_address(program_id), false)],
+        ),
+        data: Instruction::SetAuthority {
+            authority_type,
+            new_authority: *new_authority,
+        }
+        .pack(),
+    }
+}
+
+pub fn propose_admin(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    signers: &[&Pubkey],
+    new_admin: &Pubkey,
+) -> SolInstruction {
+    SolInstruction {
+        program_id: *program_id,
+        accounts: authority_accounts(
+            admin,
+            signers,
+            vec![AccountMeta::new(get_authority_address(program_id), false)],
+        ),
+        data: Instruction::ProposeAdmin {
+            new_admin: *new_admin,
+        }
+        .pack(),
+    }
+}
+
+pub fn accept_admin(
+    program_id: &Pubkey,
+    pending_admin: &Pubkey,
+    signers: &[&Pubkey],
+) -> SolInstruction {
+    SolInstruction {
+        program_id: *program_id,
+        accounts: authority_accounts(
+            pending_admin,
+            signers,
+            vec![AccountMeta::new(get_authority_address(program_id), false)],
+        ),
+        data: Instruction::AcceptAdmin.pack(),
+    }
+}
+
+/// Irreversible, clears every role but the guardian and the ballot creator so no parameter can
+/// be changed again.
+pub fn renounce_admin(program_id: &Pubkey, admin: &Pubkey, signers: &[&Pubkey]) -> SolInstruction {
+    SolInstruction {
+        program_id: *program_id,
+        accounts: authority_accounts(
+            admin,
+            signers,
+            vec![AccountMeta::new(get_authority_address(program_id), false)],
+        ),
+        data: Instruction::RenounceAdmin.pack(),
+    }
+}
+
+/// Lets a multisig authority approve `add_to_rewards_pool`, `vote` or `create_vesting`
+/// while the first account still signs for its own tokens.
+pub fn with_multisig_authority(
+    mut instruction: SolInstruction,
+    multisig: &Pubkey,
+    signers: &[&Pubkey],
+) -> SolInstruction {
+    instruction
+        .accounts
+        .push(AccountMeta::new_readonly(*multisig, false));
+    for signer in signers {
+        instruction
+            .accounts
+            .push(AccountMeta::new_readonly(**signer, true));
+    }
+    instruction
+}
+
+/// The multisig account must already be allocated with `Multisig::LEN` bytes and
+/// owned by the program. The signers do not need to sign.
+pub fn initialize_multisig(
+    program_id: &Pubkey,
+    multisig: &Pubkey,
+    signers: &[&Pubkey],
+    m: u8,
+) -> SolInstruction {
+    let mut accounts = vec![AccountMeta::new(*multisig, false)];
+    for signer in signers {
+        accounts.push(AccountMeta::new_readonly(**signer, false));
+    }
+    SolInstruction {
+        program_id: *program_id,
+        accounts,
+        data: Instruction::InitializeMultisig { m }.pack(),
+    }
+}
+
+/// `paused` is a set of `RewardsPool::PAUSE_*` flags. The guardian can only add flags.
+pub fn set_pause(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    signers: &[&Pubkey],
+    rewards: &Pubkey,
+    paused: u8,
+) -> SolInstruction {
+    SolInstruction {
+        program_id: *program_id,
+        accounts: authority_accounts(
+            authority,
+            signers,
+            vec![
+                AccountMeta::new(*rewards, false),
+                AccountMeta::new_readonly(get_authority_address(program_id), false),
+            ],
+        ),
+        data: Instruction::SetPause { paused }.pack(),
+    }
+}
+
+pub fn withdraw_from_pool(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    signers: &[&Pubkey],
+    admin_token: &Pubkey,
+    rewards: &Pubkey,
+    bucket: PoolBucket,
+    amount: u64,
+) -> SolInstruction {
+    SolInstruction {
+        program_id: *program_id,
+        accounts: authority_accounts(
+            admin,
+            signers,
+            vec![
+                AccountMeta::new(*admin_token, false),
+                AccountMeta::new_readonly(get_pda_address(program_id), false),
+                AccountMeta::new(pda_token_address(program_id), false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+                AccountMeta::new(*rewards, false),
+                AccountMeta::new_readonly(get_authority_address(program_id), false),
+            ],
+        ),
+        data: Instruction::WithdrawFromPool { bucket, amount }.pack(),
+    }
+}
+
+pub fn rebalance(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    signers: &[&Pubkey],
+    rewards: &Pubkey,
+    from: PoolBucket,
+    to: PoolBucket,
+    amount: u64,
+) -> SolInstruction {
+    SolInstruction {
+        program_id: *program_id,
+        accounts: authority_accounts(
+            admin,
+            signers,
+            vec![
+                AccountMeta::new(*rewards, false),
+                AccountMeta::new_readonly(get_authority_address(program_id), false),
+            ],
+        ),
+        data: Instruction::Rebalance { from, to, amount }.pack(),
+    }
+}
+
+/// Copies a legacy `Stake`, `RewardsPool` or `Ballot` into `new_account`, which must be
+/// allocated with the current `LEN` and owned by the program. `authority` is the stake
+/// owner, or the pool admin for pools and ballots, and receives the legacy account's rent.
+pub fn migrate_account(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    signers: &[&Pubkey],
+    legacy_account: &Pubkey,
+    new_account: &Pubkey,
+) -> SolInstruction {
+    let mut accounts = vec![
+        AccountMeta::new(*authority, signers.is_empty()),
+        AccountMeta::new(*legacy_account, false),
+        AccountMeta::new(*new_account, false),
+        AccountMeta::new_readonly(get_authority_address(program_id), false),
+    ];
+    for signer in signers {
+        accounts.push(AccountMeta::new_readonly(**signer, true));
+    }
+    SolInstruction {
+        program_id: *program_id,
+        accounts,
+        data: Instruction::MigrateAccount.pack(),
+    }
+}
+
+/// Read-only, meant for `simulateTransaction`: returns a Borsh `RewardSimulation` through the
+/// return data. With `stake` it is that stake's pending reward `duration` seconds from now and
+/// `amount` is ignored, without it the reward a new stake of `amount` collects over `duration`.
+pub fn simulate_reward(
+    program_id: &Pubkey,
+    rewards: &Pubkey,
+    stake: Option<&Pubkey>,
+    amount: u64,
+    duration: i64,
+) -> SolInstruction {
+    let mut accounts = vec![AccountMeta::new_readonly(*rewards, false)];
+    if let Some(stake) = stake {
+        accounts.push(AccountMeta::new_readonly(*stake, false));
+    }
+    SolInstruction {
+        program_id: *program_id,
+        accounts,
+        data: Instruction::SimulateReward { amount, duration }.pack(),
+    }
 }
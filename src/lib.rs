@@ -1,3 +1,56 @@
 pub mod processor;
 pub mod state;
-pub mod instruction;
\ No newline at end of file
+pub mod instruction;
+pub mod event;
+pub mod error;
+pub mod math;
+pub mod reward;
+pub mod cpi;
+pub mod return_data;
+pub mod zero_copy;
+#[cfg(feature = "indexer")]
+pub mod indexer;
+
+use solana_program::pubkey::Pubkey;
+
+/// Address of the program account that owns the staking vault.
+pub fn get_pda_address(program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[], program_id).0
+}
+
+/// Address of the account tracking a wallet's airdrop claims.
+pub fn get_airdrop_receipt_address(program_id: &Pubkey, owner: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[processor::AIRDROP_RECEIPT_SEED, owner.as_ref()],
+        program_id,
+    )
+    .0
+}
+
+/// Address of the account holding a wallet's vesting airdrop schedule.
+pub fn get_airdrop_vesting_address(program_id: &Pubkey, owner: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[processor::AIRDROP_VESTING_SEED, owner.as_ref()],
+        program_id,
+    )
+    .0
+}
+
+/// Address of a team vesting contract, `id` tells apart several contracts
+/// for the same beneficiary.
+pub fn get_vesting_contract_address(program_id: &Pubkey, beneficiary: &Pubkey, id: u64) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            processor::VESTING_CONTRACT_SEED,
+            beneficiary.as_ref(),
+            &id.to_le_bytes(),
+        ],
+        program_id,
+    )
+    .0
+}
+
+/// Address of the account holding the program's admin roles.
+pub fn get_authority_address(program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[processor::AUTHORITY_SEED], program_id).0
+}
@@ -0,0 +1,55 @@
+//! Checked arithmetic for amounts, counters and timestamps. Anything that leaves the type's
+//! range fails with `StakingError::MathOverflow` instead of panicking or wrapping.
+use crate::error::StakingError;
+use solana_program::program_error::ProgramError;
+use std::convert::TryFrom;
+
+pub trait CheckedMath: Sized {
+    fn try_add(self, rhs: Self) -> Result<Self, ProgramError>;
+    fn try_sub(self, rhs: Self) -> Result<Self, ProgramError>;
+    fn try_mul(self, rhs: Self) -> Result<Self, ProgramError>;
+    fn try_div(self, rhs: Self) -> Result<Self, ProgramError>;
+}
+
+macro_rules! impl_checked_math {
+    ($($t:ty),*) => {$(
+        impl CheckedMath for $t {
+            fn try_add(self, rhs: Self) -> Result<Self, ProgramError> {
+                self.checked_add(rhs).ok_or_else(|| StakingError::MathOverflow.into())
+            }
+
+            fn try_sub(self, rhs: Self) -> Result<Self, ProgramError> {
+                self.checked_sub(rhs).ok_or_else(|| StakingError::MathOverflow.into())
+            }
+
+            fn try_mul(self, rhs: Self) -> Result<Self, ProgramError> {
+                self.checked_mul(rhs).ok_or_else(|| StakingError::MathOverflow.into())
+            }
+
+            fn try_div(self, rhs: Self) -> Result<Self, ProgramError> {
+                self.checked_div(rhs).ok_or_else(|| StakingError::MathOverflow.into())
+            }
+        }
+    )*};
+}
+
+impl_checked_math!(u32, u64, u128, i64, i128);
+
+/// `value * numerator / denominator` through a u128, so nothing is lost to dividing first.
+pub fn mul_div(value: u64, numerator: u64, denominator: u64) -> Result<u64, ProgramError> {
+    to_u64(
+        (value as u128)
+            .try_mul(numerator as u128)?
+            .try_div(denominator as u128)?,
+    )
+}
+
+/// `value / divisor` rounded up, for amounts the pool has to cover.
+pub fn ceil_div(value: u128, divisor: u128) -> Result<u128, ProgramError> {
+    value.try_add(divisor.try_sub(1)?)?.try_div(divisor)
+}
+
+/// Narrows a u128 intermediate back to a token amount.
+pub fn to_u64(value: u128) -> Result<u64, ProgramError> {
+    u64::try_from(value).map_err(|_| StakingError::MathOverflow.into())
+}
@@ -2,33 +2,50 @@ use borsh::{BorshDeserialize, BorshSerialize};
 use solana_program::{
     account_info::{next_account_info, AccountInfo},
     clock::Clock,
-    entrypoint,
     entrypoint::ProgramResult,
     msg,
     program::{invoke, invoke_signed},
     program_error::ProgramError,
+    program_pack::Pack,
     pubkey::Pubkey,
-    sysvar::Sysvar,
+    system_instruction,
+    sysvar::{rent::Rent, Sysvar},
 };
+use std::cell::RefMut;
 
+use crate::event::Event;
 use crate::instruction::Instruction;
-use crate::state::{Ballot, RewardsPool, Stake};
+use crate::math::CheckedMath;
+use crate::return_data::{
+    self, Airdropped, PoolBalances, Released, StakeCreated, StakeEnded, Voted,
+};
+use crate::reward::{self, RewardSimulation};
+use crate::state::{
+    legacy, AccountHeader, AccountState, AirdropReceipt, Authority, AuthorityType, Ballot,
+    Multisig, PoolBucket, RewardsPool, Stake, VestingContract, VestingSchedule, MAX_SIGNERS,
+};
+use crate::zero_copy::{self, BallotData, RewardsPoolData, ZeroCopy};
 
-entrypoint!(process_instruction);
+#[cfg(not(feature = "no-entrypoint"))]
+solana_program::entrypoint!(process_instruction);
 
-const KITTYCOIN_MINT: [u8; 32] = [
+pub const KITTYCOIN_MINT: [u8; 32] = [
     83, 253, 12, 237, 188, 72, 195, 47, 36, 136, 47, 129, 204, 109, 25, 144, 91, 81, 3, 78, 116,
     125, 93, 233, 32, 239, 68, 27, 127, 118, 163, 167,
 ];
-const ADMIN_ADDRESS: [u8; 32] = [
+pub const ADMIN_ADDRESS: [u8; 32] = [
     252, 183, 216, 215, 153, 134, 231, 182, 0, 96, 138, 106, 16, 14, 99, 194, 5, 112, 181, 170,
     137, 219, 8, 176, 131, 117, 4, 201, 41, 234, 154, 123,
 ];
-const BASE_COIN: u64 = 1000000;
-const COINS_FOR_1_INTEREST: u64 = 2160000; //Number of base coins to stake to earn 1 interest per minute.
+pub const BASE_COIN: u64 = 1000000;
+pub const COINS_FOR_1_INTEREST: u64 = 2160000; //Number of base coins to stake to earn 1 interest per minute.
 const VOTING_FEE: u64 = 1000 * BASE_COIN;
 const DEFUALT_AIRDROP_COST: u64 = solana_program::native_token::LAMPORTS_PER_SOL / 10000;
-const AIRDROP_MAX: u64 = 200_000 * BASE_COIN;
+pub const AIRDROP_MAX: u64 = 200_000 * BASE_COIN;
+pub const AIRDROP_RECEIPT_SEED: &[u8] = b"airdrop";
+pub const AIRDROP_VESTING_SEED: &[u8] = b"airdrop_vesting";
+pub const VESTING_CONTRACT_SEED: &[u8] = b"vesting";
+pub const AUTHORITY_SEED: &[u8] = b"authority";
 
 pub fn process_instruction(
     program_id: &Pubkey,
@@ -37,8 +54,6 @@ pub fn process_instruction(
 ) -> ProgramResult {
     msg!("PROGRAM CALL");
 
-    msg!("{:?}", instruction_data);
-
     let instruction = Instruction::unpack(instruction_data)?;
 
     match instruction {
@@ -66,15 +81,107 @@ pub fn process_instruction(
             msg!("Instruction: ChangeAidropFee");
             process_change_airdrop_fee(program_id, accounts, fee)
         }
+        Instruction::SetAirdropLimits {
+            wallet_cap,
+            cooldown,
+        } => {
+            msg!("Instruction: SetAirdropLimits");
+            process_set_airdrop_limits(program_id, accounts, wallet_cap, cooldown)
+        }
+        Instruction::SetAirdropTreasury { fee_mint, treasury } => {
+            msg!("Instruction: SetAirdropTreasury");
+            process_set_airdrop_treasury(program_id, accounts, fee_mint, treasury)
+        }
+        Instruction::SetAirdropVesting { cliff, duration } => {
+            msg!("Instruction: SetAirdropVesting");
+            process_set_airdrop_vesting(program_id, accounts, cliff, duration)
+        }
+        Instruction::ReleaseVested => {
+            msg!("Instruction: ReleaseVested");
+            process_release_vested(program_id, accounts)
+        }
+        Instruction::CreateVesting {
+            id,
+            start,
+            cliff,
+            end,
+            amount,
+            revocable,
+        } => {
+            msg!("Instruction: CreateVesting");
+            process_create_vesting(
+                program_id, accounts, id, start, cliff, end, amount, revocable,
+            )
+        }
+        Instruction::Withdraw => {
+            msg!("Instruction: Withdraw");
+            process_withdraw(program_id, accounts)
+        }
+        Instruction::RevokeVesting => {
+            msg!("Instruction: RevokeVesting");
+            process_revoke_vesting(program_id, accounts)
+        }
+        Instruction::InitializeAuthority => {
+            msg!("Instruction: InitializeAuthority");
+            process_initialize_authority(program_id, accounts)
+        }
+        Instruction::SetAuthority {
+            authority_type,
+            new_authority,
+        } => {
+            msg!("Instruction: SetAuthority");
+            process_set_authority(program_id, accounts, authority_type, new_authority)
+        }
+        Instruction::ProposeAdmin { new_admin } => {
+            msg!("Instruction: ProposeAdmin");
+            process_propose_admin(program_id, accounts, new_admin)
+        }
+        Instruction::AcceptAdmin => {
+            msg!("Instruction: AcceptAdmin");
+            process_accept_admin(program_id, accounts)
+        }
+        Instruction::RenounceAdmin => {
+            msg!("Instruction: RenounceAdmin");
+            process_renounce_admin(program_id, accounts)
+        }
+        Instruction::InitializeMultisig { m } => {
+            msg!("Instruction: InitializeMultisig");
+            process_initialize_multisig(program_id, accounts, m)
+        }
+        Instruction::SetPause { paused } => {
+            msg!("Instruction: SetPause");
+            process_set_pause(program_id, accounts, paused)
+        }
+        Instruction::EmergencyWithdraw => {
+            msg!("Instruction: EmergencyWithdraw");
+            process_emergency_withdraw(program_id, accounts)
+        }
+        Instruction::WithdrawFromPool { bucket, amount } => {
+            msg!("Instruction: WithdrawFromPool");
+            process_withdraw_from_pool(program_id, accounts, bucket, amount)
+        }
+        Instruction::Rebalance { from, to, amount } => {
+            msg!("Instruction: Rebalance");
+            process_rebalance(program_id, accounts, from, to, amount)
+        }
+        Instruction::MigrateAccount => {
+            msg!("Instruction: MigrateAccount");
+            process_migrate_account(program_id, accounts)
+        }
+        Instruction::SimulateReward { amount, duration } => {
+            msg!("Instruction: SimulateReward");
+            process_simulate_reward(program_id, accounts, amount, duration)
+        }
     }
 }
 
-///[0] owner
+///[0] owner, or only the funding authority when [6] is given
 ///[1] owner token
 ///[2] stake
 ///[3] pda token
 ///[4] token program
 ///[5] rewards account
+///[6] stake owner, optional
 fn process_create_stake(
     program_id: &Pubkey,
     accounts: &[AccountInfo],
@@ -88,6 +195,16 @@ fn process_create_stake(
     let pda_token_account = next_account_info(accounts_iter)?;
     let token_program = next_account_info(accounts_iter)?;
     let rewards_account = next_account_info(accounts_iter)?;
+    //Lets a program stake on behalf of its PDA, which signs through invoke_signed.
+    let stake_owner = match accounts_iter.next() {
+        Some(stake_owner) => {
+            if !stake_owner.is_signer {
+                return Err(ProgramError::MissingRequiredSignature);
+            }
+            stake_owner
+        }
+        None => owner_account,
+    };
 
     let (pda, _bump_seed) = Pubkey::find_program_address(&[], program_id);
 
@@ -96,12 +213,19 @@ fn process_create_stake(
         return Err(ProgramError::InvalidInstructionData);
     }
 
-    let mut stake_info = Stake::try_from_slice(&stake_account.data.borrow())?;
+    let mut rewards_info = load_view::<RewardsPoolData>(program_id, rewards_account)?;
+    if !rewards_info.is_initialized() {
+        return Err(ProgramError::UninitializedAccount);
+    }
+    check_not_paused(rewards_info.paused, RewardsPool::PAUSE_STAKING)?;
+
+    let mut stake_info = load_new_account::<Stake>(program_id, stake_account)?;
     if stake_info.is_initialized {
         return Err(ProgramError::AccountAlreadyInitialized);
     }
+    stake_info.header = Stake::header();
     stake_info.is_initialized = true;
-    stake_info.owner = *owner_account.key;
+    stake_info.owner = *stake_owner.key;
     stake_info.amount = amount;
     stake_info.time_started = Clock::get()?.unix_timestamp;
 
@@ -139,18 +263,23 @@ fn process_create_stake(
         ],
     )?;
 
+    rewards_info.total_stakes_count = rewards_info.total_stakes_count.try_add(1)?;
+    rewards_info.total_coins_staked = rewards_info.total_coins_staked.try_add(amount)?;
 
-    let mut rewards_info = RewardsPool::try_from_slice(&rewards_account.data.borrow())?;
-    if !rewards_info.is_initialized {
-        return Err(ProgramError::UninitializedAccount);
-    }
-    rewards_info.total_stakes_count += 1;
-    rewards_info.total_coins_staked += amount;
-
-    rewards_info.serialize(&mut &mut rewards_account.data.borrow_mut()[..])?;
     stake_info.serialize(&mut &mut stake_account.data.borrow_mut()[..])?;
 
-    Ok(())
+    Event::StakeCreated {
+        owner: stake_info.owner,
+        stake: *stake_account.key,
+        amount,
+        time_started: stake_info.time_started,
+    }
+    .emit()?;
+    return_data::set(&StakeCreated {
+        amount,
+        owner_balance: token_balance(owner_token_account)?,
+        total_coins_staked: rewards_info.total_coins_staked,
+    })
 }
 
 ///[0] owner
@@ -173,15 +302,12 @@ fn process_end_stake(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramRe
 
     let (pda, bump_seed) = Pubkey::find_program_address(&[], program_id);
 
-    let mut rewards_info = RewardsPool::try_from_slice(&rewards_account.data.borrow())?;
-    if !rewards_info.is_initialized {
+    let mut rewards_info = load_view::<RewardsPoolData>(program_id, rewards_account)?;
+    if !rewards_info.is_initialized() {
         return Err(ProgramError::UninitializedAccount);
     }
-    if rewards_account.owner != program_id {
-        return Err(ProgramError::IllegalOwner);
-    }
 
-    let mut stake_info = Stake::try_from_slice(&stake_account.data.borrow())?;
+    let mut stake_info = load_account::<Stake>(program_id, stake_account, true)?;
     if !stake_info.is_initialized {
         return Err(ProgramError::UninitializedAccount);
     }
@@ -204,23 +330,17 @@ fn process_end_stake(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramRe
         return Err(ProgramError::InvalidAccountData);
     }
 
-    let current_time = Clock::get()?.unix_timestamp;
-    if current_time < stake_info.time_started {
-        return Err(ProgramError::InvalidInstructionData);
-    }
-    let time_elapsed = current_time - stake_info.time_started;
-    let minutes_elapsed: u64 = (time_elapsed / 60) as u64;
-    msg!("MINUTES ELAPSED: {}", minutes_elapsed);
-
-    let reward = stake_info.amount / COINS_FOR_1_INTEREST * minutes_elapsed;
-
-    let mut payout = stake_info.amount;
-    if rewards_info.amount >= reward {
-        payout += reward;
-        rewards_info.amount -= reward;
+    let settlement =
+        reward::settle_stake(&stake_info, &mut rewards_info, Clock::get()?.unix_timestamp)?;
+    let (reward, payout) = (settlement.reward, settlement.payout);
+    if rewards_info.is_paused(RewardsPool::PAUSE_REWARDS) {
+        msg!("Rewards are paused, returning principal only.");
+    } else if reward < settlement.accrued {
+        msg!(
+            "Pool cannot pay {} in rewards, returning principal only.",
+            settlement.accrued
+        );
     }
-    rewards_info.total_stakes_count -= 1;
-    rewards_info.total_coins_staked -= stake_info.amount;
 
     let ix = spl_token::instruction::transfer(
         token_program.key,
@@ -250,12 +370,153 @@ fn process_end_stake(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramRe
     **stake_account.lamports.borrow_mut() = 0;
 
     stake_info.is_initialized = false;
-    rewards_info.serialize(&mut &mut rewards_account.data.borrow_mut()[..])?;
     stake_info.serialize(&mut &mut stake_account.data.borrow_mut()[..])?;
 
-    msg!("Stake ended. Coins received: {}", payout);
-    msg!("Coins remaining in reward pool {}", rewards_info.amount);
-    Ok(())
+    Event::StakeEnded {
+        owner: stake_info.owner,
+        stake: *stake_account.key,
+        principal: stake_info.amount,
+        reward: payout.try_sub(stake_info.amount)?,
+    }
+    .emit()?;
+    return_data::set(&StakeEnded {
+        principal: stake_info.amount,
+        reward,
+        payout,
+        owner_balance: token_balance(owner_token_account)?,
+        pool_remaining: rewards_info.amount,
+    })
+}
+
+///[0] owner
+///[1] owner token
+///[2] stake
+///[3] pda
+///[4] pda token
+///[5] token program
+///[6] rewards
+fn process_emergency_withdraw(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+
+    let owner_account = next_account_info(accounts_iter)?;
+    let owner_token_account = next_account_info(accounts_iter)?;
+    let stake_account = next_account_info(accounts_iter)?;
+    let pda_account = next_account_info(accounts_iter)?;
+    let pda_token_account = next_account_info(accounts_iter)?;
+    let token_program = next_account_info(accounts_iter)?;
+    let rewards_account = next_account_info(accounts_iter)?;
+
+    let (pda, bump_seed) = Pubkey::find_program_address(&[], program_id);
+
+    let mut stake_info = load_account::<Stake>(program_id, stake_account, true)?;
+    if !stake_info.is_initialized {
+        return Err(ProgramError::UninitializedAccount);
+    }
+    if !owner_account.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if stake_info.owner != *owner_account.key {
+        return Err(ProgramError::IllegalOwner);
+    }
+    let mint = Pubkey::new_from_array(KITTYCOIN_MINT);
+    let associated_pda_account =
+        spl_associated_token_account::get_associated_token_address(&pda, &mint);
+
+    if associated_pda_account != *pda_token_account.key {
+        msg!(
+            "{:?} not equal to {:?}",
+            associated_pda_account,
+            *pda_token_account.key
+        );
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    //No reward math and no pause check, the owner can always get the principal back, even from
+    //a pool whose version or counters are broken. Those are logged and left for the admin.
+    let pool_remaining = match load_view::<RewardsPoolData>(program_id, rewards_account) {
+        Ok(mut rewards_info) if rewards_info.is_initialized() => {
+            let counters = (
+                { rewards_info.total_stakes_count }.try_sub(1),
+                { rewards_info.total_coins_staked }.try_sub(stake_info.amount),
+            );
+            match counters {
+                (Ok(total_stakes_count), Ok(total_coins_staked)) => {
+                    rewards_info.total_stakes_count = total_stakes_count;
+                    rewards_info.total_coins_staked = total_coins_staked;
+                }
+                _ => {
+                    msg!("Pool counters do not cover the stake!");
+                    Event::PoolAccountingSkipped {
+                        rewards: *rewards_account.key,
+                        stake: *stake_account.key,
+                    }
+                    .emit()?;
+                }
+            }
+            rewards_info.amount
+        }
+        _ => {
+            //Still has to be one of our pools, anything else would let the stake leave without
+            //the real pool counting it out.
+            let is_pool = rewards_account.owner == program_id
+                && AccountHeader::deserialize(&mut &rewards_account.data.borrow()[..])
+                    .is_ok_and(|header| header.discriminator == RewardsPool::DISCRIMINATOR);
+            if !is_pool {
+                return Err(ProgramError::InvalidAccountData);
+            }
+            check_writable(rewards_account)?;
+            msg!("Unreadable rewards pool!");
+            Event::PoolAccountingSkipped {
+                rewards: *rewards_account.key,
+                stake: *stake_account.key,
+            }
+            .emit()?;
+            0
+        }
+    };
+
+    let ix = spl_token::instruction::transfer(
+        token_program.key,
+        pda_token_account.key,
+        owner_token_account.key,
+        pda_account.key,
+        &[pda_account.key],
+        stake_info.amount,
+    )?;
+
+    invoke_signed(
+        &ix,
+        &[
+            pda_token_account.clone(),
+            owner_token_account.clone(),
+            pda_account.clone(),
+            token_program.clone(),
+        ],
+        &[&[&[], &[bump_seed]]],
+    )?;
+
+    **owner_account.lamports.borrow_mut() = owner_account
+        .lamports()
+        .checked_add(stake_account.lamports())
+        .ok_or(ProgramError::InsufficientFunds)?;
+    **stake_account.lamports.borrow_mut() = 0;
+
+    stake_info.is_initialized = false;
+    stake_info.serialize(&mut &mut stake_account.data.borrow_mut()[..])?;
+
+    Event::EmergencyWithdrawn {
+        owner: stake_info.owner,
+        stake: *stake_account.key,
+        amount: stake_info.amount,
+    }
+    .emit()?;
+    return_data::set(&StakeEnded {
+        principal: stake_info.amount,
+        reward: 0,
+        payout: stake_info.amount,
+        owner_balance: token_balance(owner_token_account)?,
+        pool_remaining,
+    })
 }
 
 ///[0] donator
@@ -263,6 +524,8 @@ fn process_end_stake(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramRe
 ///[2] pda token
 ///[3] token program
 ///[4] rewards
+///[5] authority
+///[6] pool admin multisig, optional, followed by its signers
 fn process_add_to_rewards_pool(
     program_id: &Pubkey,
     accounts: &[AccountInfo],
@@ -276,20 +539,26 @@ fn process_add_to_rewards_pool(
     let pda_token_account = next_account_info(accounts_iter)?;
     let token_program = next_account_info(accounts_iter)?;
     let rewards_account = next_account_info(accounts_iter)?;
+    let authority_account = next_account_info(accounts_iter)?;
 
     let (pda, _bump_seed) = Pubkey::find_program_address(&[], program_id);
 
-    let mut rewards_info = RewardsPool::try_from_slice(&rewards_account.data.borrow())?;
+    let mut rewards_info = load_new_view::<RewardsPoolData>(program_id, rewards_account)?;
     if !admin_account.is_signer {
         return Err(ProgramError::MissingRequiredSignature);
     }
-    if !rewards_info.is_initialized {
-        if *admin_account.key != Pubkey::new_from_array(ADMIN_ADDRESS) {
-            return Err(ProgramError::InvalidInstructionData);
-        } else {
-            rewards_info.is_initialized = true;
-            rewards_info.airdrop_fee = DEFUALT_AIRDROP_COST;
-        }
+    if !rewards_info.is_initialized() {
+        let pool_admin_account = accounts_iter.next().unwrap_or(admin_account);
+        let authority_info = check_authority(
+            program_id,
+            authority_account,
+            pool_admin_account,
+            accounts_iter.as_slice(),
+            AuthorityType::PoolAdmin,
+        )?;
+        rewards_info.is_initialized = 1;
+        rewards_info.airdrop_fee = DEFUALT_AIRDROP_COST;
+        rewards_info.airdrop_treasury = authority_info.treasury;
     }
 
     let mint = Pubkey::new_from_array(KITTYCOIN_MINT);
@@ -323,13 +592,24 @@ fn process_add_to_rewards_pool(
             token_program.clone(),
         ],
     )?;
-    if airdrop {
-        rewards_info.airdrop_supply += amount;
+    let bucket = if airdrop {
+        PoolBucket::Airdrop
     } else {
-        rewards_info.amount += amount;
+        PoolBucket::Rewards
+    };
+    let balance = rewards_info.bucket(bucket).try_add(amount)?;
+    rewards_info.set_bucket(bucket, balance);
+
+    Event::RewardsAdded {
+        donator: *admin_account.key,
+        amount,
+        bucket,
     }
-    rewards_info.serialize(&mut &mut rewards_account.data.borrow_mut()[..])?;
-    Ok(())
+    .emit()?;
+    return_data::set(&PoolBalances {
+        rewards: rewards_info.amount,
+        airdrop: rewards_info.airdrop_supply,
+    })
 }
 
 ///[0] owner
@@ -338,7 +618,8 @@ fn process_add_to_rewards_pool(
 ///[3] pda token
 ///[4] token program
 ///[5] rewards
-///[6] rewards
+///[6] authority
+///[7] ballot creator multisig, optional, followed by its signers
 fn process_vote(program_id: &Pubkey, accounts: &[AccountInfo], selection: u8) -> ProgramResult {
     let accounts_iter = &mut accounts.iter();
 
@@ -348,23 +629,29 @@ fn process_vote(program_id: &Pubkey, accounts: &[AccountInfo], selection: u8) ->
     let pda_token_account = next_account_info(accounts_iter)?;
     let token_program = next_account_info(accounts_iter)?;
     let rewards_account = next_account_info(accounts_iter)?;
+    let authority_account = next_account_info(accounts_iter)?;
 
     let (pda, _bump_seed) = Pubkey::find_program_address(&[], program_id);
 
-    let mut ballot_info = Ballot::try_from_slice(&ballot_account.data.borrow())?;
-    if !ballot_info.is_initialized {
-        if *owner_account.key == Pubkey::new_from_array(ADMIN_ADDRESS) && owner_account.is_signer {
-            //The admin can start a new ballot.
-            ballot_info.is_initialized = true;
-        } else {
-            return Err(ProgramError::UninitializedAccount);
-        }
+    let mut ballot_info = load_new_view::<BallotData>(program_id, ballot_account)?;
+    if !ballot_info.is_initialized() {
+        //The ballot creator can start a new ballot.
+        let ballot_creator_account = accounts_iter.next().unwrap_or(owner_account);
+        check_authority(
+            program_id,
+            authority_account,
+            ballot_creator_account,
+            accounts_iter.as_slice(),
+            AuthorityType::BallotCreator,
+        )?;
+        ballot_info.is_initialized = 1;
     }
 
-    let mut rewards_info = RewardsPool::try_from_slice(&rewards_account.data.borrow())?;
-    if !rewards_info.is_initialized {
+    let mut rewards_info = load_view::<RewardsPoolData>(program_id, rewards_account)?;
+    if !rewards_info.is_initialized() {
         return Err(ProgramError::UninitializedAccount);
     }
+    check_not_paused(rewards_info.paused, RewardsPool::PAUSE_VOTING)?;
 
     let mint = Pubkey::new_from_array(KITTYCOIN_MINT);
     let associated_pda_account =
@@ -398,12 +685,20 @@ fn process_vote(program_id: &Pubkey, accounts: &[AccountInfo], selection: u8) ->
         ],
     )?;
 
-    ballot_info.choices[selection as usize] += 1;
-    rewards_info.amount += VOTING_FEE;
+    ballot_info.record_vote(selection)?;
+    rewards_info.amount = rewards_info.amount.try_add(VOTING_FEE)?;
 
-    ballot_info.serialize(&mut &mut ballot_account.data.borrow_mut()[..])?;
-    rewards_info.serialize(&mut &mut rewards_account.data.borrow_mut()[..])?;
-    Ok(())
+    Event::Voted {
+        voter: *owner_account.key,
+        ballot: *ballot_account.key,
+        selection,
+        fee: VOTING_FEE,
+    }
+    .emit()?;
+    return_data::set(&Voted {
+        choices: ballot_info.choices,
+        owner_balance: token_balance(owner_token_account)?,
+    })
 }
 
 ///[0] owner
@@ -413,6 +708,10 @@ fn process_vote(program_id: &Pubkey, accounts: &[AccountInfo], selection: u8) ->
 ///[4] token program
 ///[5] rewards
 ///[6] treasury
+///[7] airdrop receipt
+///[8] system program
+///[9] vesting schedule
+///[10] owner fee token, only when the fee is paid in an SPL token
 fn process_airdrop(program_id: &Pubkey, accounts: &[AccountInfo], amount: u64) -> ProgramResult {
     let accounts_iter = &mut accounts.iter();
 
@@ -423,20 +722,31 @@ fn process_airdrop(program_id: &Pubkey, accounts: &[AccountInfo], amount: u64) -
     let token_program = next_account_info(accounts_iter)?;
     let rewards_account = next_account_info(accounts_iter)?;
     let treasury_account = next_account_info(accounts_iter)?;
+    let receipt_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    let vesting_account = next_account_info(accounts_iter)?;
 
     let (pda, bump_seed) = Pubkey::find_program_address(&[], program_id);
 
-    if *treasury_account.key != Pubkey::new_from_array(ADMIN_ADDRESS) {
-        msg!("Incorrect treasury address!");
-        return Err(ProgramError::InvalidInstructionData);
+    if !owner_account.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
     }
 
-    let mut rewards_info = RewardsPool::try_from_slice(&rewards_account.data.borrow())?;
-    if !rewards_info.is_initialized {
+    let mut rewards_info = load_view::<RewardsPoolData>(program_id, rewards_account)?;
+    if !rewards_info.is_initialized() {
         return Err(ProgramError::UninitializedAccount);
     }
-    if rewards_account.owner != program_id {
-        return Err(ProgramError::IllegalOwner);
+    check_not_paused(rewards_info.paused, RewardsPool::PAUSE_AIRDROP)?;
+    if *treasury_account.key != rewards_info.airdrop_treasury {
+        msg!("Incorrect treasury address!");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    if rewards_info.airdrop_fee_mint != Pubkey::default() {
+        let treasury_info = spl_token::state::Account::unpack(&treasury_account.data.borrow())?;
+        if treasury_info.mint != rewards_info.airdrop_fee_mint {
+            msg!("Treasury does not hold the fee mint!");
+            return Err(ProgramError::InvalidAccountData);
+        }
     }
 
     let mint = Pubkey::new_from_array(KITTYCOIN_MINT);
@@ -452,77 +762,1617 @@ fn process_airdrop(program_id: &Pubkey, accounts: &[AccountInfo], amount: u64) -
         return Err(ProgramError::InvalidAccountData);
     }
 
-    let ix = spl_token::instruction::transfer(
-        token_program.key,
-        pda_token_account.key,
-        owner_token_account.key,
-        pda_account.key,
-        &[&pda_account.key],
-        amount,
-    )?;
-
-    invoke_signed(
-        &ix,
-        &[
-            pda_token_account.clone(),
-            owner_token_account.clone(),
-            pda_account.clone(),
-            token_program.clone(),
-        ],
-        &[&[&[], &[bump_seed]]],
-    )?;
-
-    **owner_account.lamports.borrow_mut() = owner_account
-        .lamports()
-        .checked_sub(rewards_info.airdrop_fee)
-        .ok_or(ProgramError::InsufficientFunds)?;
-    **treasury_account.lamports.borrow_mut() = treasury_account
-        .lamports()
-        .checked_add(rewards_info.airdrop_fee)
-        .ok_or(ProgramError::InsufficientFunds)?;
-
     if amount > AIRDROP_MAX {
+        msg!("Maximum {} coins per airdrop.", AIRDROP_MAX);
         return Err(ProgramError::InvalidInstructionData);
     }
     if rewards_info.airdrop_supply < amount {
         return Err(ProgramError::InsufficientFunds);
     }
-    rewards_info.airdrop_supply -= amount;
-    rewards_info.serialize(&mut &mut rewards_account.data.borrow_mut()[..])?;
 
-    msg!("Coins airdropped {}", amount);
-    msg!(
-        "Coins remaining in airdrop pool {}",
-        rewards_info.airdrop_supply
+    let (receipt_pda, receipt_bump_seed) = Pubkey::find_program_address(
+        &[AIRDROP_RECEIPT_SEED, owner_account.key.as_ref()],
+        program_id,
     );
-    Ok(())
+    if receipt_pda != *receipt_account.key {
+        msg!("Incorrect airdrop receipt address!");
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let mut receipt_info = if receipt_account.data_is_empty() {
+        AirdropReceipt {
+            header: AirdropReceipt::header(),
+            is_initialized: false,
+            owner: *owner_account.key,
+            total_claimed: 0,
+            last_claim_time: 0,
+        }
+    } else {
+        load_new_account::<AirdropReceipt>(program_id, receipt_account)?
+    };
+
+    let current_time = Clock::get()?.unix_timestamp;
+    let next_claim_time = receipt_info
+        .last_claim_time
+        .try_add(rewards_info.airdrop_cooldown)?;
+    if receipt_info.is_initialized && current_time < next_claim_time {
+        msg!("Airdrop cooldown active, next claim at {}", next_claim_time);
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    let total_claimed = receipt_info.total_claimed.try_add(amount)?;
+    if rewards_info.airdrop_wallet_cap > 0 && total_claimed > rewards_info.airdrop_wallet_cap {
+        msg!(
+            "Airdrop wallet cap reached, {} already claimed",
+            receipt_info.total_claimed
+        );
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    let vesting = rewards_info.airdrop_vesting_duration > 0;
+    let (vesting_pda, vesting_bump_seed) = Pubkey::find_program_address(
+        &[AIRDROP_VESTING_SEED, owner_account.key.as_ref()],
+        program_id,
+    );
+    let mut vesting_info = VestingSchedule::default();
+    if vesting {
+        if vesting_pda != *vesting_account.key {
+            msg!("Incorrect vesting schedule address!");
+            return Err(ProgramError::InvalidAccountData);
+        }
+        if !vesting_account.data_is_empty() {
+            vesting_info = load_new_account(program_id, vesting_account)?;
+        }
+        if vesting_info.is_initialized && vesting_info.released < vesting_info.total {
+            msg!("Previous airdrop is still vesting!");
+            return Err(ProgramError::InvalidInstructionData);
+        }
+    }
+
+    if receipt_account.data_is_empty() {
+        create_pda_account(
+            owner_account,
+            receipt_account,
+            system_program,
+            AirdropReceipt::LEN,
+            program_id,
+            &[
+                AIRDROP_RECEIPT_SEED,
+                owner_account.key.as_ref(),
+                &[receipt_bump_seed],
+            ],
+        )?;
+    }
+    if vesting && vesting_account.data_is_empty() {
+        create_pda_account(
+            owner_account,
+            vesting_account,
+            system_program,
+            VestingSchedule::LEN,
+            program_id,
+            &[
+                AIRDROP_VESTING_SEED,
+                owner_account.key.as_ref(),
+                &[vesting_bump_seed],
+            ],
+        )?;
+    }
+
+    if rewards_info.airdrop_fee > 0 && rewards_info.airdrop_fee_mint == Pubkey::default() {
+        let ix = system_instruction::transfer(
+            owner_account.key,
+            treasury_account.key,
+            rewards_info.airdrop_fee,
+        );
+
+        invoke(
+            &ix,
+            &[
+                owner_account.clone(),
+                treasury_account.clone(),
+                system_program.clone(),
+            ],
+        )?;
+    } else if rewards_info.airdrop_fee > 0 {
+        let owner_fee_token_account = next_account_info(accounts_iter)?;
+
+        let ix = spl_token::instruction::transfer(
+            token_program.key,
+            owner_fee_token_account.key,
+            treasury_account.key,
+            owner_account.key,
+            &[owner_account.key],
+            rewards_info.airdrop_fee,
+        )?;
+
+        invoke(
+            &ix,
+            &[
+                owner_fee_token_account.clone(),
+                treasury_account.clone(),
+                owner_account.clone(),
+                token_program.clone(),
+            ],
+        )?;
+
+        if treasury_account.key == pda_token_account.key {
+            //Fees paid into the vault fund staking rewards.
+            rewards_info.amount = rewards_info.amount.try_add(rewards_info.airdrop_fee)?;
+        }
+    }
+
+    if vesting {
+        //The coins stay in the vault until released by the schedule.
+        vesting_info = VestingSchedule {
+            header: VestingSchedule::header(),
+            is_initialized: true,
+            beneficiary: *owner_account.key,
+            start_time: current_time,
+            cliff_time: current_time.try_add(rewards_info.airdrop_vesting_cliff)?,
+            end_time: current_time.try_add(rewards_info.airdrop_vesting_duration)?,
+            total: amount,
+            released: 0,
+        };
+        rewards_info.vesting_locked = rewards_info.vesting_locked.try_add(amount)?;
+        vesting_info.serialize(&mut &mut vesting_account.data.borrow_mut()[..])?;
+    } else {
+        let ix = spl_token::instruction::transfer(
+            token_program.key,
+            pda_token_account.key,
+            owner_token_account.key,
+            pda_account.key,
+            &[pda_account.key],
+            amount,
+        )?;
+
+        invoke_signed(
+            &ix,
+            &[
+                pda_token_account.clone(),
+                owner_token_account.clone(),
+                pda_account.clone(),
+                token_program.clone(),
+            ],
+            &[&[&[], &[bump_seed]]],
+        )?;
+    }
+
+    rewards_info.airdrop_supply = rewards_info.airdrop_supply.try_sub(amount)?;
+    receipt_info.header = AirdropReceipt::header();
+    receipt_info.is_initialized = true;
+    receipt_info.owner = *owner_account.key;
+    receipt_info.total_claimed = total_claimed;
+    receipt_info.last_claim_time = current_time;
+    receipt_info.serialize(&mut &mut receipt_account.data.borrow_mut()[..])?;
+
+    Event::Airdropped {
+        owner: *owner_account.key,
+        amount,
+        fee: rewards_info.airdrop_fee,
+        vesting,
+    }
+    .emit()?;
+    return_data::set(&Airdropped {
+        amount,
+        fee: rewards_info.airdrop_fee,
+        vesting,
+        owner_balance: token_balance(owner_token_account)?,
+        total_claimed: receipt_info.total_claimed,
+        airdrop_remaining: rewards_info.airdrop_supply,
+    })
 }
 
 ///[0] admin
 ///[1] rewards
-fn process_change_airdrop_fee(program_id: &Pubkey, accounts: &[AccountInfo], fee: u64) -> ProgramResult {
+///[2] authority
+///[3..] multisig signers, when [0] is a multisig
+fn process_change_airdrop_fee(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    fee: u64,
+) -> ProgramResult {
     let accounts_iter = &mut accounts.iter();
 
     let admin_account = next_account_info(accounts_iter)?;
     let rewards_account = next_account_info(accounts_iter)?;
+    let authority_account = next_account_info(accounts_iter)?;
+
+    check_authority(
+        program_id,
+        authority_account,
+        admin_account,
+        accounts_iter.as_slice(),
+        AuthorityType::FeeManager,
+    )?;
 
-    if *admin_account.key != Pubkey::new_from_array(ADMIN_ADDRESS) || !admin_account.is_signer {
-        msg!("Incorrect admin address!");
+    let mut rewards_info = load_view::<RewardsPoolData>(program_id, rewards_account)?;
+    if !rewards_info.is_initialized() {
+        return Err(ProgramError::UninitializedAccount);
+    }
+
+    rewards_info.airdrop_fee = fee;
+
+    Event::FeeChanged { fee }.emit()
+}
+
+///[0] admin
+///[1] rewards
+///[2] authority
+///[3..] multisig signers, when [0] is a multisig
+fn process_set_airdrop_limits(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    wallet_cap: u64,
+    cooldown: i64,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+
+    let admin_account = next_account_info(accounts_iter)?;
+    let rewards_account = next_account_info(accounts_iter)?;
+    let authority_account = next_account_info(accounts_iter)?;
+
+    check_authority(
+        program_id,
+        authority_account,
+        admin_account,
+        accounts_iter.as_slice(),
+        AuthorityType::PoolAdmin,
+    )?;
+    if cooldown < 0 {
         return Err(ProgramError::InvalidInstructionData);
     }
 
-    let mut rewards_info = RewardsPool::try_from_slice(&rewards_account.data.borrow())?;
-    if !rewards_info.is_initialized {
+    let mut rewards_info = load_view::<RewardsPoolData>(program_id, rewards_account)?;
+    if !rewards_info.is_initialized() {
         return Err(ProgramError::UninitializedAccount);
     }
-    if rewards_account.owner != program_id {
-        return Err(ProgramError::IllegalOwner);
+
+    rewards_info.airdrop_wallet_cap = wallet_cap;
+    rewards_info.airdrop_cooldown = cooldown;
+
+    Event::AirdropLimitsChanged {
+        wallet_cap,
+        cooldown,
     }
+    .emit()
+}
 
-    rewards_info.airdrop_fee = fee;
-    rewards_info.serialize(&mut &mut rewards_account.data.borrow_mut()[..])?;
+///[0] admin
+///[1] rewards
+///[2] authority
+///[3..] multisig signers, when [0] is a multisig
+fn process_set_airdrop_treasury(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    fee_mint: Pubkey,
+    treasury: Pubkey,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+
+    let admin_account = next_account_info(accounts_iter)?;
+    let rewards_account = next_account_info(accounts_iter)?;
+    let authority_account = next_account_info(accounts_iter)?;
+
+    check_authority(
+        program_id,
+        authority_account,
+        admin_account,
+        accounts_iter.as_slice(),
+        AuthorityType::Treasury,
+    )?;
 
-    msg!("Airdrop fee changed to {} lamports", fee);
+    let mut rewards_info = load_view::<RewardsPoolData>(program_id, rewards_account)?;
+    if !rewards_info.is_initialized() {
+        return Err(ProgramError::UninitializedAccount);
+    }
 
-    Ok(())
+    rewards_info.airdrop_fee_mint = fee_mint;
+    rewards_info.airdrop_treasury = treasury;
+
+    Event::AirdropTreasuryChanged { fee_mint, treasury }.emit()
+}
+
+///[0] admin
+///[1] rewards
+///[2] authority
+///[3..] multisig signers, when [0] is a multisig
+fn process_set_airdrop_vesting(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    cliff: i64,
+    duration: i64,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+
+    let admin_account = next_account_info(accounts_iter)?;
+    let rewards_account = next_account_info(accounts_iter)?;
+    let authority_account = next_account_info(accounts_iter)?;
+
+    check_authority(
+        program_id,
+        authority_account,
+        admin_account,
+        accounts_iter.as_slice(),
+        AuthorityType::PoolAdmin,
+    )?;
+    if cliff < 0 || duration < 0 || cliff > duration {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    let mut rewards_info = load_view::<RewardsPoolData>(program_id, rewards_account)?;
+    if !rewards_info.is_initialized() {
+        return Err(ProgramError::UninitializedAccount);
+    }
+
+    rewards_info.airdrop_vesting_cliff = cliff;
+    rewards_info.airdrop_vesting_duration = duration;
+
+    Event::AirdropVestingChanged { cliff, duration }.emit()
+}
+
+///[0] beneficiary
+///[1] beneficiary token
+///[2] vesting schedule
+///[3] pda
+///[4] pda token
+///[5] token program
+///[6] rewards
+fn process_release_vested(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+
+    let beneficiary_account = next_account_info(accounts_iter)?;
+    let beneficiary_token_account = next_account_info(accounts_iter)?;
+    let vesting_account = next_account_info(accounts_iter)?;
+    let pda_account = next_account_info(accounts_iter)?;
+    let pda_token_account = next_account_info(accounts_iter)?;
+    let token_program = next_account_info(accounts_iter)?;
+    let rewards_account = next_account_info(accounts_iter)?;
+
+    let (pda, bump_seed) = Pubkey::find_program_address(&[], program_id);
+
+    if !beneficiary_account.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let mut rewards_info = load_view::<RewardsPoolData>(program_id, rewards_account)?;
+    if !rewards_info.is_initialized() {
+        return Err(ProgramError::UninitializedAccount);
+    }
+    check_not_paused(rewards_info.paused, RewardsPool::PAUSE_AIRDROP)?;
+
+    let mut vesting_info = load_account::<VestingSchedule>(program_id, vesting_account, true)?;
+    if !vesting_info.is_initialized {
+        return Err(ProgramError::UninitializedAccount);
+    }
+    if vesting_info.beneficiary != *beneficiary_account.key {
+        return Err(ProgramError::IllegalOwner);
+    }
+
+    let mint = Pubkey::new_from_array(KITTYCOIN_MINT);
+    let associated_pda_account =
+        spl_associated_token_account::get_associated_token_address(&pda, &mint);
+
+    if associated_pda_account != *pda_token_account.key {
+        msg!(
+            "{:?} not equal to {:?}",
+            associated_pda_account,
+            *pda_token_account.key
+        );
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let current_time = Clock::get()?.unix_timestamp;
+    let releasable = vesting_info
+        .vested_amount(current_time)?
+        .try_sub(vesting_info.released)?;
+    if releasable == 0 {
+        msg!("Nothing to release until {}", vesting_info.cliff_time);
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    let ix = spl_token::instruction::transfer(
+        token_program.key,
+        pda_token_account.key,
+        beneficiary_token_account.key,
+        pda_account.key,
+        &[pda_account.key],
+        releasable,
+    )?;
+
+    invoke_signed(
+        &ix,
+        &[
+            pda_token_account.clone(),
+            beneficiary_token_account.clone(),
+            pda_account.clone(),
+            token_program.clone(),
+        ],
+        &[&[&[], &[bump_seed]]],
+    )?;
+
+    vesting_info.released = vesting_info.released.try_add(releasable)?;
+    rewards_info.vesting_locked = rewards_info.vesting_locked.try_sub(releasable)?;
+    vesting_info.serialize(&mut &mut vesting_account.data.borrow_mut()[..])?;
+
+    Event::VestedReleased {
+        beneficiary: vesting_info.beneficiary,
+        schedule: *vesting_account.key,
+        amount: releasable,
+    }
+    .emit()?;
+    return_data::set(&Released {
+        amount: releasable,
+        beneficiary_balance: token_balance(beneficiary_token_account)?,
+        still_vesting: vesting_info.total.try_sub(vesting_info.released)?,
+    })
+}
+
+///[0] admin
+///[1] admin token
+///[2] beneficiary
+///[3] vesting contract
+///[4] vesting vault
+///[5] token program
+///[6] system program
+///[7] authority
+///[8] pool admin multisig, optional, followed by its signers
+#[allow(clippy::too_many_arguments)]
+fn process_create_vesting(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    id: u64,
+    start: i64,
+    cliff: i64,
+    end: i64,
+    amount: u64,
+    revocable: bool,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+
+    let admin_account = next_account_info(accounts_iter)?;
+    let admin_token_account = next_account_info(accounts_iter)?;
+    let beneficiary_account = next_account_info(accounts_iter)?;
+    let vesting_account = next_account_info(accounts_iter)?;
+    let vault_account = next_account_info(accounts_iter)?;
+    let token_program = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    let authority_account = next_account_info(accounts_iter)?;
+    let pool_admin_account = accounts_iter.next().unwrap_or(admin_account);
+
+    check_authority(
+        program_id,
+        authority_account,
+        pool_admin_account,
+        accounts_iter.as_slice(),
+        AuthorityType::PoolAdmin,
+    )?;
+    if amount == 0 || start < 0 || start > cliff || cliff > end || start >= end {
+        msg!("Invalid vesting schedule.");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    let (vesting_pda, vesting_bump_seed) = Pubkey::find_program_address(
+        &[
+            VESTING_CONTRACT_SEED,
+            beneficiary_account.key.as_ref(),
+            &id.to_le_bytes(),
+        ],
+        program_id,
+    );
+    if vesting_pda != *vesting_account.key {
+        msg!("Incorrect vesting contract address!");
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if !vesting_account.data_is_empty() {
+        let vesting_info = load_new_account::<VestingContract>(program_id, vesting_account)?;
+        if vesting_info.is_initialized {
+            return Err(ProgramError::AccountAlreadyInitialized);
+        }
+    }
+
+    let mint = Pubkey::new_from_array(KITTYCOIN_MINT);
+    let associated_vault_account =
+        spl_associated_token_account::get_associated_token_address(&vesting_pda, &mint);
+
+    if associated_vault_account != *vault_account.key {
+        msg!(
+            "{:?} not equal to {:?}",
+            associated_vault_account,
+            *vault_account.key
+        );
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if vesting_account.data_is_empty() {
+        create_pda_account(
+            admin_account,
+            vesting_account,
+            system_program,
+            VestingContract::LEN,
+            program_id,
+            &[
+                VESTING_CONTRACT_SEED,
+                beneficiary_account.key.as_ref(),
+                &id.to_le_bytes(),
+                &[vesting_bump_seed],
+            ],
+        )?;
+    }
+
+    let ix = spl_token::instruction::transfer(
+        token_program.key,
+        admin_token_account.key,
+        vault_account.key,
+        admin_account.key,
+        &[admin_account.key],
+        amount,
+    )?;
+
+    invoke(
+        &ix,
+        &[
+            admin_token_account.clone(),
+            vault_account.clone(),
+            admin_account.clone(),
+            token_program.clone(),
+        ],
+    )?;
+
+    let vesting_info = VestingContract {
+        header: VestingContract::header(),
+        is_initialized: true,
+        beneficiary: *beneficiary_account.key,
+        id,
+        start_time: start,
+        cliff_time: cliff,
+        end_time: end,
+        total: amount,
+        released: 0,
+        revocable,
+    };
+    vesting_info.serialize(&mut &mut vesting_account.data.borrow_mut()[..])?;
+
+    Event::VestingCreated {
+        beneficiary: vesting_info.beneficiary,
+        contract: *vesting_account.key,
+        id,
+        amount,
+        start,
+        cliff,
+        end,
+        revocable,
+    }
+    .emit()
+}
+
+///[0] beneficiary
+///[1] beneficiary token
+///[2] vesting contract
+///[3] vesting vault
+///[4] token program
+fn process_withdraw(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+
+    let beneficiary_account = next_account_info(accounts_iter)?;
+    let beneficiary_token_account = next_account_info(accounts_iter)?;
+    let vesting_account = next_account_info(accounts_iter)?;
+    let vault_account = next_account_info(accounts_iter)?;
+    let token_program = next_account_info(accounts_iter)?;
+
+    if !beneficiary_account.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let mut vesting_info = load_vesting_contract(program_id, vesting_account, vault_account)?;
+    if vesting_info.beneficiary != *beneficiary_account.key {
+        return Err(ProgramError::IllegalOwner);
+    }
+
+    let current_time = Clock::get()?.unix_timestamp;
+    let releasable = vesting_info
+        .vested_amount(current_time)?
+        .try_sub(vesting_info.released)?;
+    if releasable == 0 {
+        msg!("Nothing to withdraw until {}", vesting_info.cliff_time);
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    transfer_from_vesting_vault(
+        &vesting_info,
+        vesting_account,
+        vault_account,
+        beneficiary_token_account,
+        token_program,
+        program_id,
+        releasable,
+    )?;
+
+    vesting_info.released = vesting_info.released.try_add(releasable)?;
+    vesting_info.serialize(&mut &mut vesting_account.data.borrow_mut()[..])?;
+
+    Event::VestingWithdrawn {
+        beneficiary: vesting_info.beneficiary,
+        contract: *vesting_account.key,
+        amount: releasable,
+    }
+    .emit()?;
+    return_data::set(&Released {
+        amount: releasable,
+        beneficiary_balance: token_balance(beneficiary_token_account)?,
+        still_vesting: vesting_info.total.try_sub(vesting_info.released)?,
+    })
+}
+
+///[0] admin
+///[1] admin token
+///[2] vesting contract
+///[3] vesting vault
+///[4] token program
+///[5] authority
+///[6..] multisig signers, when [0] is a multisig
+fn process_revoke_vesting(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+
+    let admin_account = next_account_info(accounts_iter)?;
+    let admin_token_account = next_account_info(accounts_iter)?;
+    let vesting_account = next_account_info(accounts_iter)?;
+    let vault_account = next_account_info(accounts_iter)?;
+    let token_program = next_account_info(accounts_iter)?;
+    let authority_account = next_account_info(accounts_iter)?;
+
+    check_authority(
+        program_id,
+        authority_account,
+        admin_account,
+        accounts_iter.as_slice(),
+        AuthorityType::PoolAdmin,
+    )?;
+
+    let mut vesting_info = load_vesting_contract(program_id, vesting_account, vault_account)?;
+    if !vesting_info.revocable {
+        msg!("Vesting contract is not revocable.");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    let current_time = Clock::get()?.unix_timestamp;
+    let vested = vesting_info.vested_amount(current_time)?;
+    let unvested = vesting_info.total.try_sub(vested)?;
+
+    if unvested > 0 {
+        transfer_from_vesting_vault(
+            &vesting_info,
+            vesting_account,
+            vault_account,
+            admin_token_account,
+            token_program,
+            program_id,
+            unvested,
+        )?;
+    }
+
+    //Freeze the schedule at what has vested so far, the beneficiary can still withdraw it.
+    vesting_info.total = vested;
+    vesting_info.end_time = vesting_info.end_time.min(current_time);
+    vesting_info.cliff_time = vesting_info.cliff_time.min(vesting_info.end_time);
+    vesting_info.revocable = false;
+    vesting_info.serialize(&mut &mut vesting_account.data.borrow_mut()[..])?;
+
+    Event::VestingRevoked {
+        contract: *vesting_account.key,
+        returned: unvested,
+    }
+    .emit()
+}
+
+///[0] admin
+///[1] authority
+///[2] system program
+fn process_initialize_authority(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+
+    let admin_account = next_account_info(accounts_iter)?;
+    let authority_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+
+    //The deploy key only bootstraps the authority account, every role can be handed off after.
+    if *admin_account.key != Pubkey::new_from_array(ADMIN_ADDRESS) || !admin_account.is_signer {
+        msg!("Incorrect admin address!");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    let (authority_pda, authority_bump_seed) =
+        Pubkey::find_program_address(&[AUTHORITY_SEED], program_id);
+    if authority_pda != *authority_account.key {
+        msg!("Incorrect authority address!");
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if authority_account.data_is_empty() {
+        create_pda_account(
+            admin_account,
+            authority_account,
+            system_program,
+            Authority::LEN,
+            program_id,
+            &[AUTHORITY_SEED, &[authority_bump_seed]],
+        )?;
+    }
+
+    let mut authority_info = load_new_account::<Authority>(program_id, authority_account)?;
+    if authority_info.is_initialized {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+    authority_info.header = Authority::header();
+    authority_info.is_initialized = true;
+    authority_info.pool_admin = *admin_account.key;
+    authority_info.fee_manager = *admin_account.key;
+    authority_info.ballot_creator = *admin_account.key;
+    authority_info.treasury = *admin_account.key;
+    authority_info.guardian = *admin_account.key;
+    authority_info.serialize(&mut &mut authority_account.data.borrow_mut()[..])?;
+
+    Event::AuthorityInitialized {
+        admin: *admin_account.key,
+    }
+    .emit()
+}
+
+///[0] current authority, either the pool admin or the holder of the role
+///[1] authority
+///[2..] multisig signers, when [0] is a multisig
+fn process_set_authority(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    authority_type: AuthorityType,
+    new_authority: Pubkey,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+
+    let current_authority_account = next_account_info(accounts_iter)?;
+    let authority_account = next_account_info(accounts_iter)?;
+    check_writable(authority_account)?;
+
+    let mut authority_info = load_authority(program_id, authority_account)?;
+    if authority_info.renounced {
+        msg!("Authority renounced!");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    //The pool admin only moves through ProposeAdmin and AcceptAdmin.
+    if authority_type == AuthorityType::PoolAdmin {
+        msg!("Use ProposeAdmin to change the PoolAdmin!");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    let signers = accounts_iter.as_slice();
+    if validate_owner(
+        program_id,
+        &authority_info.pool_admin,
+        current_authority_account,
+        signers,
+    )
+    .is_err()
+        && validate_owner(
+            program_id,
+            &authority_info.get(authority_type),
+            current_authority_account,
+            signers,
+        )
+        .is_err()
+    {
+        msg!("Incorrect {:?} address!", authority_type);
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    authority_info.set(authority_type, new_authority);
+    authority_info.serialize(&mut &mut authority_account.data.borrow_mut()[..])?;
+
+    Event::AuthorityChanged {
+        authority_type,
+        new_authority,
+    }
+    .emit()
+}
+
+///[0] pool admin
+///[1] authority
+///[2..] multisig signers, when [0] is a multisig
+fn process_propose_admin(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    new_admin: Pubkey,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+
+    let admin_account = next_account_info(accounts_iter)?;
+    let authority_account = next_account_info(accounts_iter)?;
+    check_writable(authority_account)?;
+
+    let mut authority_info = check_authority(
+        program_id,
+        authority_account,
+        admin_account,
+        accounts_iter.as_slice(),
+        AuthorityType::PoolAdmin,
+    )?;
+
+    //Proposing the default key cancels a pending transfer.
+    authority_info.pending_admin = new_admin;
+    authority_info.serialize(&mut &mut authority_account.data.borrow_mut()[..])?;
+
+    Event::AdminProposed { new_admin }.emit()
+}
+
+///[0] pending admin
+///[1] authority
+///[2..] multisig signers, when [0] is a multisig
+fn process_accept_admin(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+
+    let pending_admin_account = next_account_info(accounts_iter)?;
+    let authority_account = next_account_info(accounts_iter)?;
+    check_writable(authority_account)?;
+
+    let mut authority_info = load_authority(program_id, authority_account)?;
+    if authority_info.renounced {
+        msg!("Authority renounced!");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    if authority_info.pending_admin == Pubkey::default()
+        || validate_owner(
+            program_id,
+            &authority_info.pending_admin,
+            pending_admin_account,
+            accounts_iter.as_slice(),
+        )
+        .is_err()
+    {
+        msg!("Incorrect pending admin address!");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    authority_info.pool_admin = authority_info.pending_admin;
+    authority_info.pending_admin = Pubkey::default();
+    authority_info.serialize(&mut &mut authority_account.data.borrow_mut()[..])?;
+
+    Event::AdminAccepted {
+        new_admin: authority_info.pool_admin,
+    }
+    .emit()
+}
+
+///[0] pool admin
+///[1] authority
+///[2..] multisig signers, when [0] is a multisig
+fn process_renounce_admin(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+
+    let admin_account = next_account_info(accounts_iter)?;
+    let authority_account = next_account_info(accounts_iter)?;
+    check_writable(authority_account)?;
+
+    let current = check_authority(
+        program_id,
+        authority_account,
+        admin_account,
+        accounts_iter.as_slice(),
+        AuthorityType::PoolAdmin,
+    )?;
+
+    //No key is left that could change a pool parameter or hand a role back out. The guardian
+    //and the ballot creator stay, so the pool can still be paused, resumed and voted on.
+    let authority_info = Authority {
+        header: Authority::header(),
+        is_initialized: true,
+        ballot_creator: current.ballot_creator,
+        guardian: current.guardian,
+        renounced: true,
+        ..Authority::default()
+    };
+    authority_info.serialize(&mut &mut authority_account.data.borrow_mut()[..])?;
+
+    Event::AdminRenounced.emit()
+}
+
+///[0] guardian or pool admin
+///[1] rewards
+///[2] authority
+///[3..] multisig signers, when [0] is a multisig
+fn process_set_pause(program_id: &Pubkey, accounts: &[AccountInfo], paused: u8) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+
+    let admin_account = next_account_info(accounts_iter)?;
+    let rewards_account = next_account_info(accounts_iter)?;
+    let authority_account = next_account_info(accounts_iter)?;
+    let signers = accounts_iter.as_slice();
+
+    let mut rewards_info = load_view::<RewardsPoolData>(program_id, rewards_account)?;
+    if !rewards_info.is_initialized() {
+        return Err(ProgramError::UninitializedAccount);
+    }
+
+    //The guardian can halt the pool on its own, resuming takes the pool admin.
+    let is_pool_admin = check_authority(
+        program_id,
+        authority_account,
+        admin_account,
+        signers,
+        AuthorityType::PoolAdmin,
+    )
+    .is_ok();
+    if !is_pool_admin {
+        let authority_info = check_authority(
+            program_id,
+            authority_account,
+            admin_account,
+            signers,
+            AuthorityType::Guardian,
+        )?;
+        //Once no pool admin is left, the guardian resumes the pool too.
+        if !authority_info.renounced && paused & rewards_info.paused != rewards_info.paused {
+            msg!("Only the pool admin can unpause.");
+            return Err(ProgramError::InvalidInstructionData);
+        }
+    }
+
+    rewards_info.paused = paused;
+
+    Event::PauseChanged { paused }.emit()
+}
+
+///[0] admin
+///[1] admin token
+///[2] pda
+///[3] pda token
+///[4] token program
+///[5] rewards
+///[6] authority
+///[7..] multisig signers, when [0] is a multisig
+fn process_withdraw_from_pool(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    bucket: PoolBucket,
+    amount: u64,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+
+    let admin_account = next_account_info(accounts_iter)?;
+    let admin_token_account = next_account_info(accounts_iter)?;
+    let pda_account = next_account_info(accounts_iter)?;
+    let pda_token_account = next_account_info(accounts_iter)?;
+    let token_program = next_account_info(accounts_iter)?;
+    let rewards_account = next_account_info(accounts_iter)?;
+    let authority_account = next_account_info(accounts_iter)?;
+
+    check_authority(
+        program_id,
+        authority_account,
+        admin_account,
+        accounts_iter.as_slice(),
+        AuthorityType::PoolAdmin,
+    )?;
+
+    let (pda, bump_seed) = Pubkey::find_program_address(&[], program_id);
+
+    let mut rewards_info = load_view::<RewardsPoolData>(program_id, rewards_account)?;
+    if !rewards_info.is_initialized() {
+        return Err(ProgramError::UninitializedAccount);
+    }
+
+    let mint = Pubkey::new_from_array(KITTYCOIN_MINT);
+    let associated_pda_account =
+        spl_associated_token_account::get_associated_token_address(&pda, &mint);
+
+    if associated_pda_account != *pda_token_account.key {
+        msg!(
+            "{:?} not equal to {:?}",
+            associated_pda_account,
+            *pda_token_account.key
+        );
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if amount > rewards_info.bucket(bucket) {
+        msg!(
+            "Only {} coins in the {:?} bucket.",
+            rewards_info.bucket(bucket),
+            bucket
+        );
+        return Err(ProgramError::InsufficientFunds);
+    }
+
+    //Staked principal and vesting schedules must stay backed by the vault after the withdraw.
+    let vault_balance = spl_token::state::Account::unpack(&pda_token_account.data.borrow())?.amount;
+    let reserved = rewards_info
+        .total_coins_staked
+        .try_add(rewards_info.vesting_locked)?;
+    if vault_balance < reserved.try_add(amount)? {
+        msg!(
+            "Vault holds {}, {} is reserved for stakes and vesting.",
+            vault_balance,
+            reserved
+        );
+        return Err(ProgramError::InsufficientFunds);
+    }
+
+    let balance = rewards_info.bucket(bucket).try_sub(amount)?;
+    rewards_info.set_bucket(bucket, balance);
+
+    let ix = spl_token::instruction::transfer(
+        token_program.key,
+        pda_token_account.key,
+        admin_token_account.key,
+        pda_account.key,
+        &[pda_account.key],
+        amount,
+    )?;
+
+    invoke_signed(
+        &ix,
+        &[
+            pda_token_account.clone(),
+            admin_token_account.clone(),
+            pda_account.clone(),
+            token_program.clone(),
+        ],
+        &[&[&[], &[bump_seed]]],
+    )?;
+
+    Event::PoolWithdrawn { bucket, amount }.emit()?;
+    return_data::set(&PoolBalances {
+        rewards: rewards_info.amount,
+        airdrop: rewards_info.airdrop_supply,
+    })
+}
+
+///[0] admin
+///[1] rewards
+///[2] authority
+///[3..] multisig signers, when [0] is a multisig
+fn process_rebalance(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    from: PoolBucket,
+    to: PoolBucket,
+    amount: u64,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+
+    let admin_account = next_account_info(accounts_iter)?;
+    let rewards_account = next_account_info(accounts_iter)?;
+    let authority_account = next_account_info(accounts_iter)?;
+
+    check_authority(
+        program_id,
+        authority_account,
+        admin_account,
+        accounts_iter.as_slice(),
+        AuthorityType::PoolAdmin,
+    )?;
+
+    let mut rewards_info = load_view::<RewardsPoolData>(program_id, rewards_account)?;
+    if !rewards_info.is_initialized() {
+        return Err(ProgramError::UninitializedAccount);
+    }
+
+    if from == to {
+        msg!("Cannot rebalance a bucket into itself.");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    if amount > rewards_info.bucket(from) {
+        msg!(
+            "Only {} coins in the {:?} bucket.",
+            rewards_info.bucket(from),
+            from
+        );
+        return Err(ProgramError::InsufficientFunds);
+    }
+
+    //Both buckets live in the same vault, only the counters move.
+    let from_balance = rewards_info.bucket(from).try_sub(amount)?;
+    let to_balance = rewards_info.bucket(to).try_add(amount)?;
+    rewards_info.set_bucket(from, from_balance);
+    rewards_info.set_bucket(to, to_balance);
+
+    Event::Rebalanced { from, to, amount }.emit()?;
+    return_data::set(&PoolBalances {
+        rewards: rewards_info.amount,
+        airdrop: rewards_info.airdrop_supply,
+    })
+}
+
+///[0] stake owner, or pool admin for pools and ballots
+///[1] legacy account
+///[2] new account
+///[3] authority
+///[4..] multisig signers, when [0] is a multisig
+fn process_migrate_account(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+
+    let owner_account = next_account_info(accounts_iter)?;
+    let legacy_account = next_account_info(accounts_iter)?;
+    let new_account = next_account_info(accounts_iter)?;
+    let authority_account = next_account_info(accounts_iter)?;
+
+    if legacy_account.owner != program_id || new_account.owner != program_id {
+        return Err(ProgramError::IllegalOwner);
+    }
+    if legacy_account.key == new_account.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+    //Accounts cannot be resized, so the new layout goes into a fresh zeroed account.
+    if new_account.data.borrow().iter().any(|byte| *byte != 0) {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+    if !Rent::get()?.is_exempt(new_account.lamports(), new_account.data_len()) {
+        return Err(ProgramError::AccountNotRentExempt);
+    }
+
+    //Legacy layouts have no header, their sizes tell them apart.
+    match legacy_account.data_len() {
+        legacy::Stake::LEN => {
+            let old = legacy::Stake::try_from_slice(&legacy_account.data.borrow())?;
+            if !old.is_initialized {
+                return Err(ProgramError::UninitializedAccount);
+            }
+            if old.owner != *owner_account.key || !owner_account.is_signer {
+                return Err(ProgramError::IllegalOwner);
+            }
+            let stake_info = Stake {
+                header: Stake::header(),
+                is_initialized: true,
+                owner: old.owner,
+                amount: old.amount,
+                time_started: old.time_started,
+            };
+            write_migrated(&stake_info, new_account)?;
+        }
+        legacy::RewardsPool::LEN => {
+            let old = legacy::RewardsPool::try_from_slice(&legacy_account.data.borrow())?;
+            let authority_info = check_authority(
+                program_id,
+                authority_account,
+                owner_account,
+                accounts_iter.as_slice(),
+                AuthorityType::PoolAdmin,
+            )?;
+            let rewards_info = RewardsPool {
+                header: RewardsPool::header(),
+                is_initialized: old.is_initialized,
+                amount: old.amount,
+                airdrop_supply: old.airdrop_supply,
+                airdrop_fee: old.airdrop_fee,
+                total_coins_staked: old.total_coins_staked,
+                total_stakes_count: old.total_stakes_count,
+                airdrop_treasury: authority_info.treasury,
+                ..RewardsPool::default()
+            };
+            write_migrated(&rewards_info, new_account)?;
+        }
+        legacy::Ballot::LEN => {
+            let old = legacy::Ballot::try_from_slice(&legacy_account.data.borrow())?;
+            check_authority(
+                program_id,
+                authority_account,
+                owner_account,
+                accounts_iter.as_slice(),
+                AuthorityType::PoolAdmin,
+            )?;
+            let ballot_info = Ballot {
+                header: Ballot::header(),
+                is_initialized: old.is_initialized,
+                choices: old.choices,
+            };
+            write_migrated(&ballot_info, new_account)?;
+        }
+        _ => {
+            msg!("Not a legacy account.");
+            return Err(ProgramError::InvalidAccountData);
+        }
+    }
+
+    //Close the legacy account so it cannot be migrated twice.
+    **owner_account.lamports.borrow_mut() = owner_account
+        .lamports()
+        .checked_add(legacy_account.lamports())
+        .ok_or(ProgramError::InsufficientFunds)?;
+    **legacy_account.lamports.borrow_mut() = 0;
+    legacy_account.data.borrow_mut().fill(0);
+
+    Event::AccountMigrated {
+        legacy: *legacy_account.key,
+        new: *new_account.key,
+    }
+    .emit()
+}
+
+///[0] rewards
+///[1] stake, optional
+fn process_simulate_reward(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    amount: u64,
+    duration: i64,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+
+    let rewards_account = next_account_info(accounts_iter)?;
+
+    let rewards_info = load_account::<RewardsPool>(program_id, rewards_account, false)?;
+    if !rewards_info.is_initialized {
+        return Err(ProgramError::UninitializedAccount);
+    }
+    if duration < 0 {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    let simulation = match accounts_iter.next() {
+        Some(stake_account) => {
+            let stake_info = load_account::<Stake>(program_id, stake_account, false)?;
+            if !stake_info.is_initialized {
+                return Err(ProgramError::UninitializedAccount);
+            }
+            let now = Clock::get()?.unix_timestamp;
+            RewardSimulation {
+                reward: reward::pending_reward(&stake_info, &rewards_info, now.try_add(duration)?),
+                apr_bps: reward::stake_apr_bps(&rewards_info, &stake_info),
+            }
+        }
+        None => RewardSimulation {
+            reward: reward::projected_reward(&rewards_info, amount, duration),
+            apr_bps: reward::effective_apr_bps(&rewards_info, amount),
+        },
+    };
+
+    msg!(
+        "Reward {} at {} bps APR",
+        simulation.reward,
+        simulation.apr_bps
+    );
+    return_data::set(&simulation)
+}
+
+fn write_migrated<T: AccountState>(state: &T, new_account: &AccountInfo) -> ProgramResult {
+    if new_account.data_len() != T::LEN {
+        msg!("New account must be {} bytes.", T::LEN);
+        return Err(ProgramError::InvalidAccountData);
+    }
+    state.serialize(&mut &mut new_account.data.borrow_mut()[..])?;
+    Ok(())
+}
+
+fn check_not_paused(paused: u8, flag: u8) -> ProgramResult {
+    if paused & flag != 0 {
+        msg!("Paused: {:#06b}", paused);
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    Ok(())
+}
+
+/// Deserializes a program account of type `T` after checking its owner, writability and header.
+fn load_account<T: AccountState>(
+    program_id: &Pubkey,
+    account: &AccountInfo,
+    writable: bool,
+) -> Result<T, ProgramError> {
+    check_account::<T>(program_id, account, writable)?;
+    Ok(T::try_from_slice(&account.data.borrow())?)
+}
+
+/// Borrows a writable program account as the zero-copy view `V`, with the checks of `load_account`.
+fn load_view<'a, 'b, V: ZeroCopy>(
+    program_id: &Pubkey,
+    account: &'a AccountInfo<'b>,
+) -> Result<RefMut<'a, V>, ProgramError> {
+    check_account::<V::State>(program_id, account, true)?;
+    zero_copy::view_mut(account.data.borrow_mut())
+}
+
+/// Same as `load_view` for accounts that may still be zeroed, see `load_new_account`.
+fn load_new_view<'a, 'b, V: ZeroCopy>(
+    program_id: &Pubkey,
+    account: &'a AccountInfo<'b>,
+) -> Result<RefMut<'a, V>, ProgramError> {
+    init_header::<V::State>(program_id, account)?;
+    load_view(program_id, account)
+}
+
+fn check_account<T: AccountState>(
+    program_id: &Pubkey,
+    account: &AccountInfo,
+    writable: bool,
+) -> ProgramResult {
+    if account.owner != program_id {
+        return Err(ProgramError::IllegalOwner);
+    }
+    if writable {
+        check_writable(account)?;
+    }
+    let data = account.data.borrow();
+    let header = AccountHeader::deserialize(&mut &data[..])?;
+    if header.discriminator != T::DISCRIMINATOR {
+        msg!("{} is not the expected account type.", account.key);
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if header.version != T::VERSION {
+        msg!(
+            "{} is at version {}, MigrateAccount it to {}.",
+            account.key,
+            header.version,
+            T::VERSION
+        );
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(())
+}
+
+/// Same as `load_account` for accounts that may still be zeroed, which get the header of `T`.
+/// Callers check `is_initialized` themselves.
+fn load_new_account<T: AccountState>(
+    program_id: &Pubkey,
+    account: &AccountInfo,
+) -> Result<T, ProgramError> {
+    init_header::<T>(program_id, account)?;
+    load_account(program_id, account, true)
+}
+
+fn init_header<T: AccountState>(program_id: &Pubkey, account: &AccountInfo) -> ProgramResult {
+    if account.owner != program_id {
+        return Err(ProgramError::IllegalOwner);
+    }
+    check_writable(account)?;
+    if account.data.borrow().iter().all(|byte| *byte == 0) {
+        T::header().serialize(&mut &mut account.data.borrow_mut()[..])?;
+    }
+    Ok(())
+}
+
+fn token_balance(token_account: &AccountInfo) -> Result<u64, ProgramError> {
+    Ok(spl_token::state::Account::unpack(&token_account.data.borrow())?.amount)
+}
+
+fn check_writable(account: &AccountInfo) -> ProgramResult {
+    if !account.is_writable {
+        msg!("{} must be writable.", account.key);
+        return Err(ProgramError::InvalidArgument);
+    }
+    Ok(())
+}
+
+fn load_authority(
+    program_id: &Pubkey,
+    authority_account: &AccountInfo,
+) -> Result<Authority, ProgramError> {
+    let (authority_pda, _bump_seed) = Pubkey::find_program_address(&[AUTHORITY_SEED], program_id);
+    if authority_pda != *authority_account.key {
+        msg!("Incorrect authority address!");
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let authority_info = load_account::<Authority>(program_id, authority_account, false)?;
+    if !authority_info.is_initialized {
+        return Err(ProgramError::UninitializedAccount);
+    }
+    Ok(authority_info)
+}
+
+/// Loads the authority account and checks that `signer_account` signed as the holder of `authority_type`.
+fn check_authority(
+    program_id: &Pubkey,
+    authority_account: &AccountInfo,
+    signer_account: &AccountInfo,
+    signers: &[AccountInfo],
+    authority_type: AuthorityType,
+) -> Result<Authority, ProgramError> {
+    let authority_info = load_authority(program_id, authority_account)?;
+    if authority_info.renounced && authority_type.changes_parameters() {
+        msg!("Authority renounced!");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    if let Err(e) = validate_owner(
+        program_id,
+        &authority_info.get(authority_type),
+        signer_account,
+        signers,
+    ) {
+        msg!("Incorrect {:?} address!", authority_type);
+        return Err(e);
+    }
+    Ok(authority_info)
+}
+
+/// Checks that `owner_account` is `expected_owner` and approved the instruction, either by
+/// signing itself or, for a multisig account, through at least `m` of its keys in `signers`.
+fn validate_owner(
+    program_id: &Pubkey,
+    expected_owner: &Pubkey,
+    owner_account: &AccountInfo,
+    signers: &[AccountInfo],
+) -> ProgramResult {
+    if expected_owner != owner_account.key {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    if owner_account.owner == program_id && owner_account.data_len() == Multisig::LEN {
+        let multisig = load_account::<Multisig>(program_id, owner_account, false)?;
+        if multisig.is_initialized {
+            let mut num_signers = 0;
+            let mut matched = [false; MAX_SIGNERS];
+            for signer in signers.iter() {
+                for (position, key) in multisig.signers[0..multisig.n as usize].iter().enumerate() {
+                    if key == signer.key && !matched[position] {
+                        if !signer.is_signer {
+                            return Err(ProgramError::MissingRequiredSignature);
+                        }
+                        matched[position] = true;
+                        num_signers += 1;
+                    }
+                }
+            }
+            if num_signers < multisig.m {
+                msg!("{} of {} multisig signatures", num_signers, multisig.m);
+                return Err(ProgramError::MissingRequiredSignature);
+            }
+            return Ok(());
+        }
+    }
+    if !owner_account.is_signer {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    Ok(())
+}
+
+///[0] multisig
+///[1..] signers
+fn process_initialize_multisig(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    m: u8,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+
+    let multisig_account = next_account_info(accounts_iter)?;
+    let signer_accounts = accounts_iter.as_slice();
+
+    if !Rent::get()?.is_exempt(multisig_account.lamports(), multisig_account.data_len()) {
+        return Err(ProgramError::AccountNotRentExempt);
+    }
+    let mut multisig = load_new_account::<Multisig>(program_id, multisig_account)?;
+    if multisig.is_initialized {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+    if signer_accounts.is_empty() || signer_accounts.len() > MAX_SIGNERS {
+        msg!("A multisig needs 1 to {} signers.", MAX_SIGNERS);
+        return Err(ProgramError::InvalidArgument);
+    }
+    if m == 0 || m as usize > signer_accounts.len() {
+        msg!("m must be between 1 and the number of signers.");
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    multisig.header = Multisig::header();
+    multisig.is_initialized = true;
+    multisig.m = m;
+    multisig.n = signer_accounts.len() as u8;
+    for (i, signer_account) in signer_accounts.iter().enumerate() {
+        multisig.signers[i] = *signer_account.key;
+    }
+    multisig.serialize(&mut &mut multisig_account.data.borrow_mut()[..])?;
+
+    Event::MultisigInitialized {
+        multisig: *multisig_account.key,
+        m,
+        n: multisig.n,
+    }
+    .emit()
+}
+
+fn load_vesting_contract(
+    program_id: &Pubkey,
+    vesting_account: &AccountInfo,
+    vault_account: &AccountInfo,
+) -> Result<VestingContract, ProgramError> {
+    let vesting_info = load_account::<VestingContract>(program_id, vesting_account, true)?;
+    if !vesting_info.is_initialized {
+        return Err(ProgramError::UninitializedAccount);
+    }
+
+    let mint = Pubkey::new_from_array(KITTYCOIN_MINT);
+    let associated_vault_account =
+        spl_associated_token_account::get_associated_token_address(vesting_account.key, &mint);
+
+    if associated_vault_account != *vault_account.key {
+        msg!(
+            "{:?} not equal to {:?}",
+            associated_vault_account,
+            *vault_account.key
+        );
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(vesting_info)
+}
+
+fn transfer_from_vesting_vault<'a>(
+    vesting_info: &VestingContract,
+    vesting_account: &AccountInfo<'a>,
+    vault_account: &AccountInfo<'a>,
+    destination_account: &AccountInfo<'a>,
+    token_program: &AccountInfo<'a>,
+    program_id: &Pubkey,
+    amount: u64,
+) -> ProgramResult {
+    let (vesting_pda, vesting_bump_seed) = Pubkey::find_program_address(
+        &[
+            VESTING_CONTRACT_SEED,
+            vesting_info.beneficiary.as_ref(),
+            &vesting_info.id.to_le_bytes(),
+        ],
+        program_id,
+    );
+    if vesting_pda != *vesting_account.key {
+        msg!("Incorrect vesting contract address!");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let ix = spl_token::instruction::transfer(
+        token_program.key,
+        vault_account.key,
+        destination_account.key,
+        vesting_account.key,
+        &[vesting_account.key],
+        amount,
+    )?;
+
+    invoke_signed(
+        &ix,
+        &[
+            vault_account.clone(),
+            destination_account.clone(),
+            vesting_account.clone(),
+            token_program.clone(),
+        ],
+        &[&[
+            VESTING_CONTRACT_SEED,
+            vesting_info.beneficiary.as_ref(),
+            &vesting_info.id.to_le_bytes(),
+            &[vesting_bump_seed],
+        ]],
+    )
+}
+
+fn create_pda_account<'a>(
+    payer: &AccountInfo<'a>,
+    account: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    space: usize,
+    program_id: &Pubkey,
+    seeds: &[&[u8]],
+) -> ProgramResult {
+    let rent = Rent::get()?.minimum_balance(space);
+    if account.lamports() == 0 {
+        let ix = system_instruction::create_account(
+            payer.key,
+            account.key,
+            rent,
+            space as u64,
+            program_id,
+        );
+
+        return invoke_signed(
+            &ix,
+            &[payer.clone(), account.clone(), system_program.clone()],
+            &[seeds],
+        );
+    }
+
+    //Anyone can send lamports to the address first, which makes create_account fail. Top it up
+    //to rent instead and allocate and assign it in place.
+    let top_up = rent.saturating_sub(account.lamports());
+    if top_up > 0 {
+        let ix = system_instruction::transfer(payer.key, account.key, top_up);
+
+        invoke(
+            &ix,
+            &[payer.clone(), account.clone(), system_program.clone()],
+        )?;
+    }
+
+    let ix = system_instruction::allocate(account.key, space as u64);
+    invoke_signed(&ix, &[account.clone(), system_program.clone()], &[seeds])?;
+
+    let ix = system_instruction::assign(account.key, program_id);
+    invoke_signed(&ix, &[account.clone(), system_program.clone()], &[seeds])
 }
@@ -0,0 +1,73 @@
+//! Results instructions hand back through `set_return_data`, so a calling program can react
+//! within the same transaction. Each is Borsh encoded; decode with `T::try_from_slice`.
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{
+    entrypoint::ProgramResult,
+    program::{get_return_data, set_return_data},
+    pubkey::Pubkey,
+};
+
+/// Returned by `CreateStake`.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq)]
+pub struct StakeCreated {
+    pub amount: u64,
+    pub owner_balance: u64, //Coins left in the owner's token account.
+    pub total_coins_staked: u64,
+}
+
+/// Returned by `EndStake` and `EmergencyWithdraw`, which never pays a reward.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq)]
+pub struct StakeEnded {
+    pub principal: u64,
+    pub reward: u64,
+    pub payout: u64,
+    pub owner_balance: u64,
+    pub pool_remaining: u64, //Rewards left in the pool, 0 when it could not be read.
+}
+
+/// Returned by `Airdrop`.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Airdropped {
+    pub amount: u64,
+    pub fee: u64,
+    pub vesting: bool, //The coins went to the vesting schedule, not the owner.
+    pub owner_balance: u64,
+    pub total_claimed: u64,
+    pub airdrop_remaining: u64,
+}
+
+/// Returned by `ReleaseVested` and `Withdraw`.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Released {
+    pub amount: u64,
+    pub beneficiary_balance: u64,
+    pub still_vesting: u64,
+}
+
+/// Returned by `Vote`.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Voted {
+    pub choices: [u32; 5],
+    pub owner_balance: u64,
+}
+
+/// Returned by `AddToRewardsPool`, `WithdrawFromPool` and `Rebalance`.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq)]
+pub struct PoolBalances {
+    pub rewards: u64,
+    pub airdrop: u64,
+}
+
+pub fn set<T: BorshSerialize>(result: &T) -> ProgramResult {
+    set_return_data(&result.try_to_vec()?);
+    Ok(())
+}
+
+/// What the last instruction a caller invoked in `program_id` returned, `None` when that
+/// instruction was not ours or set nothing of type `T`.
+pub fn get<T: BorshDeserialize>(program_id: &Pubkey) -> Option<T> {
+    match get_return_data() {
+        Some((returned_by, data)) if returned_by == *program_id => T::try_from_slice(&data).ok(),
+        _ => None,
+    }
+}
@@ -0,0 +1,159 @@
+//! Staking reward formula, shared by the processor and clients.
+//!
+//! Rewards accrue every second at one interest per `COINS_FOR_1_INTEREST` per minute, in u128
+//! fixed point scaled by `PRECISION`. Rounding always favours the pool: amounts paid out round
+//! down, amounts the pool must cover round up, so the pool never owes more than it accrued.
+use crate::math::{self, CheckedMath};
+use crate::processor::COINS_FOR_1_INTEREST;
+use crate::state::{RewardsPool, Stake};
+use crate::zero_copy::RewardsPoolData;
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::program_error::ProgramError;
+
+pub const SECONDS_PER_YEAR: i64 = 365 * 24 * 60 * 60;
+/// Fixed-point scale of `accrued_reward_scaled`.
+pub const PRECISION: u128 = 1_000_000_000_000;
+const BPS: u64 = 10_000;
+//Coin-seconds that earn one interest.
+const COIN_SECONDS_PER_INTEREST: u128 = COINS_FOR_1_INTEREST as u128 * 60;
+
+/// What `SimulateReward` returns through the return data.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq)]
+pub struct RewardSimulation {
+    pub reward: u64,
+    pub apr_bps: u64,
+}
+
+/// Reward accrued by `amount` staked at `time_started`, scaled by `PRECISION` and rounded
+/// down. Saturates at `u128::MAX`, far beyond any pool.
+pub fn accrued_reward_scaled(amount: u64, time_started: i64, now: i64) -> u128 {
+    if now < time_started {
+        return 0;
+    }
+    scaled_reward(amount, time_started, now).unwrap_or(u128::MAX)
+}
+
+fn scaled_reward(amount: u64, time_started: i64, now: i64) -> Result<u128, ProgramError> {
+    //Both fit in a u64, so the product fits in a u128. Splitting off the remainder keeps the
+    //scaling exact.
+    let elapsed = (now as i128).try_sub(time_started as i128)? as u128;
+    let coin_seconds = (amount as u128).try_mul(elapsed)?;
+    let whole = coin_seconds.try_div(COIN_SECONDS_PER_INTEREST)?;
+    let fraction = (coin_seconds % COIN_SECONDS_PER_INTEREST)
+        .try_mul(PRECISION)?
+        .try_div(COIN_SECONDS_PER_INTEREST)?;
+    whole.try_mul(PRECISION)?.try_add(fraction)
+}
+
+/// Reward accrued by `amount` staked at `time_started`, in whole base coins rounded down.
+/// Saturates at `u64::MAX`, more than any pool can pay.
+pub fn accrued_reward(amount: u64, time_started: i64, now: i64) -> u64 {
+    math::to_u64(accrued_reward_scaled(amount, time_started, now) / PRECISION).unwrap_or(u64::MAX)
+}
+
+/// The part of an accrued reward the pool pays out: nothing while rewards are paused, and
+/// nothing when the pool cannot cover all of it.
+pub fn payable_reward(reward: u64, pool_amount: u64, paused: u8) -> u64 {
+    if paused & RewardsPool::PAUSE_REWARDS != 0 || pool_amount < reward {
+        0
+    } else {
+        reward
+    }
+}
+
+/// What ending a stake moves out of the vault.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Settlement {
+    pub accrued: u64, //Reward earned, only paid when the pool covers it.
+    pub reward: u64,
+    pub payout: u64, //Principal and reward.
+}
+
+/// Ends `stake` at `now`, taking the reward and the principal off the pool counters. Counters
+/// that do not cover the stake mean the pool account is inconsistent, which fails with
+/// `MathOverflow` instead of wrapping, and leaves the pool untouched.
+pub fn settle_stake(
+    stake: &Stake,
+    pool: &mut RewardsPoolData,
+    now: i64,
+) -> Result<Settlement, ProgramError> {
+    if now < stake.time_started {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    let accrued = accrued_reward(stake.amount, stake.time_started, now);
+    let reward = payable_reward(accrued, pool.amount, pool.paused);
+    let payout = stake.amount.try_add(reward)?;
+    let amount = { pool.amount }.try_sub(reward)?;
+    let total_stakes_count = { pool.total_stakes_count }.try_sub(1)?;
+    let total_coins_staked = { pool.total_coins_staked }.try_sub(stake.amount)?;
+
+    pool.amount = amount;
+    pool.total_stakes_count = total_stakes_count;
+    pool.total_coins_staked = total_coins_staked;
+    Ok(Settlement {
+        accrued,
+        reward,
+        payout,
+    })
+}
+
+/// Reward `EndStake` would pay on top of the principal at `now`.
+pub fn pending_reward(stake: &Stake, pool: &RewardsPool, now: i64) -> u64 {
+    payable_reward(
+        accrued_reward(stake.amount, stake.time_started, now),
+        pool.amount,
+        pool.paused,
+    )
+}
+
+/// Reward a new stake of `amount` would collect after `duration` seconds with the pool as is.
+pub fn projected_reward(pool: &RewardsPool, amount: u64, duration: i64) -> u64 {
+    payable_reward(
+        accrued_reward(amount, 0, duration),
+        pool.amount,
+        pool.paused,
+    )
+}
+
+/// Yearly return of a new stake of `amount` in basis points. Once the pool cannot fund a year
+/// of rewards for every stake, the rate drops to what its balance covers.
+pub fn effective_apr_bps(pool: &RewardsPool, amount: u64) -> u64 {
+    apr_bps(
+        pool,
+        pool.total_coins_staked as u128 + amount as u128,
+        amount,
+    )
+}
+
+/// Same as `effective_apr_bps` for a stake already counted in the pool.
+pub fn stake_apr_bps(pool: &RewardsPool, stake: &Stake) -> u64 {
+    apr_bps(
+        pool,
+        pool.total_coins_staked.max(stake.amount) as u128,
+        stake.amount,
+    )
+}
+
+fn apr_bps(pool: &RewardsPool, staked: u128, amount: u64) -> u64 {
+    if amount == 0 || pool.is_paused(RewardsPool::PAUSE_REWARDS) {
+        return 0;
+    }
+    pool_apr_bps(pool, staked, amount).unwrap_or(u64::MAX)
+}
+
+fn pool_apr_bps(pool: &RewardsPool, staked: u128, amount: u64) -> Result<u64, ProgramError> {
+    let yearly = accrued_reward(amount, 0, SECONDS_PER_YEAR);
+    let nominal = math::mul_div(yearly, BPS, amount)?;
+
+    //What a year costs the pool, rounded up.
+    let coin_seconds = staked.try_mul(SECONDS_PER_YEAR as u128)?;
+    let owed = math::ceil_div(coin_seconds, COIN_SECONDS_PER_INTEREST)?;
+    if owed > pool.amount as u128 {
+        let covered = (pool.amount as u128)
+            .try_mul(BPS as u128)?
+            .try_div(staked)?;
+        math::to_u64(covered.min(nominal as u128))
+    } else {
+        Ok(nominal)
+    }
+}
@@ -1,5 +1,31 @@
-use solana_program::pubkey::Pubkey;
+use crate::{error::StakingError, math::{self, CheckedMath}};
+use solana_program::{program_error::ProgramError, pubkey::Pubkey};
 use borsh::{BorshDeserialize, BorshSerialize};
+use std::convert::TryFrom;
+
+/// First bytes of every program account, tells account types and layout versions apart.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Default, Clone, Copy, PartialEq)]
+pub struct AccountHeader {
+    pub discriminator: [u8; 8],
+    pub version: u8
+}
+
+impl AccountHeader {
+    pub const LEN: usize = 8 + 1;
+}
+
+pub trait AccountState: BorshSerialize + BorshDeserialize {
+    const DISCRIMINATOR: [u8; 8];
+    const VERSION: u8;
+    const LEN: usize; //Serialized size including the header.
+
+    fn header() -> AccountHeader {
+        AccountHeader {
+            discriminator: Self::DISCRIMINATOR,
+            version: Self::VERSION,
+        }
+    }
+}
 
 
 #[derive(BorshSerialize, BorshDeserialize, Debug)]
@@ -8,26 +34,294 @@ pub struct GreetingAccount {
     pub stats: [u8; 5]
 }
 
-#[derive(BorshSerialize, BorshDeserialize, Debug)]
+#[derive(BorshSerialize, BorshDeserialize, Debug, Default)]
 pub struct Stake {
+    pub header: AccountHeader,
     pub is_initialized: bool,
     pub owner: Pubkey,
     pub amount: u64,
     pub time_started: i64
 }
 
-#[derive(BorshSerialize, BorshDeserialize, Debug)]
+#[derive(BorshSerialize, BorshDeserialize, Debug, Default)]
 pub struct RewardsPool {
+    pub header: AccountHeader,
     pub is_initialized: bool,
     pub amount: u64,
     pub airdrop_supply: u64,
     pub airdrop_fee: u64,
     pub total_coins_staked: u64,
-    pub total_stakes_count: u32
+    pub total_stakes_count: u32,
+    pub airdrop_wallet_cap: u64, //Max coins one wallet can claim in total, 0 for no cap.
+    pub airdrop_cooldown: i64, //Seconds a wallet must wait between claims.
+    pub airdrop_fee_mint: Pubkey, //Default pubkey when the fee is charged in lamports.
+    pub airdrop_treasury: Pubkey,
+    pub airdrop_vesting_cliff: i64,
+    pub airdrop_vesting_duration: i64, //0 sends airdrops straight to the claimer.
+    pub vesting_locked: u64, //Coins in the vault owed to vesting schedules.
+    pub paused: u8 //PAUSE_* flags, EndStake still returns principal while rewards are paused.
 }
 
-#[derive(BorshSerialize, BorshDeserialize, Debug)]
+impl RewardsPool {
+    pub const PAUSE_STAKING: u8 = 1;
+    pub const PAUSE_VOTING: u8 = 1 << 1;
+    pub const PAUSE_AIRDROP: u8 = 1 << 2;
+    pub const PAUSE_REWARDS: u8 = 1 << 3;
+
+    pub fn is_paused(&self, flag: u8) -> bool {
+        self.paused & flag != 0
+    }
+}
+
+/// Counters in `RewardsPool` that hold coins the admin can move around.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq)]
+pub enum PoolBucket {
+    Rewards,
+    Airdrop
+}
+
+impl RewardsPool {
+    pub fn bucket(&self, bucket: PoolBucket) -> u64 {
+        match bucket {
+            PoolBucket::Rewards => self.amount,
+            PoolBucket::Airdrop => self.airdrop_supply,
+        }
+    }
+
+    pub fn bucket_mut(&mut self, bucket: PoolBucket) -> &mut u64 {
+        match bucket {
+            PoolBucket::Rewards => &mut self.amount,
+            PoolBucket::Airdrop => &mut self.airdrop_supply,
+        }
+    }
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Default)]
 pub struct Ballot {
+    pub header: AccountHeader,
     pub is_initialized: bool,
     pub choices: [u32;5]
 }
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Default)]
+pub struct AirdropReceipt {
+    pub header: AccountHeader,
+    pub is_initialized: bool,
+    pub owner: Pubkey,
+    pub total_claimed: u64,
+    pub last_claim_time: i64
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Default)]
+pub struct VestingSchedule {
+    pub header: AccountHeader,
+    pub is_initialized: bool,
+    pub beneficiary: Pubkey,
+    pub start_time: i64,
+    pub cliff_time: i64,
+    pub end_time: i64,
+    pub total: u64,
+    pub released: u64
+}
+
+impl VestingSchedule {
+    pub fn vested_amount(&self, now: i64) -> Result<u64, ProgramError> {
+        vested_amount(self.total, self.start_time, self.cliff_time, self.end_time, now)
+    }
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Default)]
+pub struct VestingContract {
+    pub header: AccountHeader,
+    pub is_initialized: bool,
+    pub beneficiary: Pubkey,
+    pub id: u64,
+    pub start_time: i64,
+    pub cliff_time: i64,
+    pub end_time: i64,
+    pub total: u64,
+    pub released: u64,
+    pub revocable: bool
+}
+
+impl VestingContract {
+    pub fn vested_amount(&self, now: i64) -> Result<u64, ProgramError> {
+        vested_amount(self.total, self.start_time, self.cliff_time, self.end_time, now)
+    }
+}
+
+/// Coins unlocked at `now`: nothing before the cliff, then linear from
+/// `start_time` until everything is unlocked at `end_time`. Fails with `MathOverflow` for a
+/// schedule whose times are too far apart or out of order.
+pub fn vested_amount(
+    total: u64,
+    start_time: i64,
+    cliff_time: i64,
+    end_time: i64,
+    now: i64,
+) -> Result<u64, ProgramError> {
+    if now < cliff_time {
+        Ok(0)
+    } else if now >= end_time {
+        Ok(total)
+    } else {
+        let elapsed = u64::try_from(now.try_sub(start_time)?).map_err(|_| StakingError::MathOverflow)?;
+        let duration = u64::try_from(end_time.try_sub(start_time)?).map_err(|_| StakingError::MathOverflow)?;
+        math::mul_div(total, elapsed, duration)
+    }
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq)]
+pub enum AuthorityType {
+    PoolAdmin,
+    FeeManager,
+    BallotCreator,
+    Treasury,
+    Guardian
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Default)]
+pub struct Authority {
+    pub header: AccountHeader,
+    pub is_initialized: bool,
+    pub pool_admin: Pubkey, //Initializes the pool and sets airdrop limits, vesting and roles.
+    pub fee_manager: Pubkey, //Changes the airdrop fee.
+    pub ballot_creator: Pubkey, //Starts new ballots.
+    pub treasury: Pubkey, //Default airdrop fee destination, can redirect airdrop fees.
+    pub guardian: Pubkey, //Can pause the pool, unpausing takes the pool admin until it renounces.
+    pub pending_admin: Pubkey, //Proposed pool admin, takes over once it accepts.
+    pub renounced: bool //Set by RenounceAdmin, parameters and roles are frozen for good.
+}
+
+impl Authority {
+    pub fn get(&self, authority_type: AuthorityType) -> Pubkey {
+        match authority_type {
+            AuthorityType::PoolAdmin => self.pool_admin,
+            AuthorityType::FeeManager => self.fee_manager,
+            AuthorityType::BallotCreator => self.ballot_creator,
+            AuthorityType::Treasury => self.treasury,
+            AuthorityType::Guardian => self.guardian,
+        }
+    }
+
+    pub fn set(&mut self, authority_type: AuthorityType, key: Pubkey) {
+        match authority_type {
+            AuthorityType::PoolAdmin => self.pool_admin = key,
+            AuthorityType::FeeManager => self.fee_manager = key,
+            AuthorityType::BallotCreator => self.ballot_creator = key,
+            AuthorityType::Treasury => self.treasury = key,
+            AuthorityType::Guardian => self.guardian = key,
+        }
+    }
+}
+
+impl AuthorityType {
+    /// Whether the role changes pool parameters, which `RenounceAdmin` takes away for good. The
+    /// guardian and the ballot creator keep running the pool after a renounce.
+    pub fn changes_parameters(self) -> bool {
+        !matches!(self, AuthorityType::BallotCreator | AuthorityType::Guardian)
+    }
+}
+
+/// Maximum number of keys a multisig authority can hold.
+pub const MAX_SIGNERS: usize = 11;
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Default)]
+pub struct Multisig {
+    pub header: AccountHeader,
+    pub is_initialized: bool,
+    pub m: u8, //Signatures required.
+    pub n: u8, //Valid entries in signers.
+    pub signers: [Pubkey; MAX_SIGNERS]
+}
+
+
+impl AccountState for Stake {
+    const DISCRIMINATOR: [u8; 8] = *b"kc:stake";
+    const VERSION: u8 = 1;
+    const LEN: usize = AccountHeader::LEN + 1 + 32 + 8 + 8;
+}
+
+impl AccountState for RewardsPool {
+    const DISCRIMINATOR: [u8; 8] = *b"kc:pool\0";
+    const VERSION: u8 = 1;
+    const LEN: usize = AccountHeader::LEN + 1 + 8 * 4 + 4 + 8 + 8 + 32 + 32 + 8 + 8 + 8 + 1;
+}
+
+impl AccountState for Ballot {
+    const DISCRIMINATOR: [u8; 8] = *b"kc:ballt";
+    const VERSION: u8 = 1;
+    const LEN: usize = AccountHeader::LEN + 1 + 4 * 5;
+}
+
+impl AccountState for AirdropReceipt {
+    const DISCRIMINATOR: [u8; 8] = *b"kc:rcpt\0";
+    const VERSION: u8 = 1;
+    const LEN: usize = AccountHeader::LEN + 1 + 32 + 8 + 8;
+}
+
+impl AccountState for VestingSchedule {
+    const DISCRIMINATOR: [u8; 8] = *b"kc:vsch\0";
+    const VERSION: u8 = 1;
+    const LEN: usize = AccountHeader::LEN + 1 + 32 + 8 + 8 + 8 + 8 + 8;
+}
+
+impl AccountState for VestingContract {
+    const DISCRIMINATOR: [u8; 8] = *b"kc:vcon\0";
+    const VERSION: u8 = 1;
+    const LEN: usize = AccountHeader::LEN + 1 + 32 + 8 + 8 + 8 + 8 + 8 + 8 + 1;
+}
+
+impl AccountState for Authority {
+    const DISCRIMINATOR: [u8; 8] = *b"kc:auth\0";
+    const VERSION: u8 = 1;
+    const LEN: usize = AccountHeader::LEN + 1 + 32 * 6 + 1;
+}
+
+impl AccountState for Multisig {
+    const DISCRIMINATOR: [u8; 8] = *b"kc:msig\0";
+    const VERSION: u8 = 1;
+    const LEN: usize = AccountHeader::LEN + 1 + 1 + 1 + 32 * MAX_SIGNERS;
+}
+
+/// Layouts written before accounts had a header, only read by MigrateAccount.
+pub mod legacy {
+    use solana_program::pubkey::Pubkey;
+    use borsh::{BorshDeserialize, BorshSerialize};
+
+    #[derive(BorshSerialize, BorshDeserialize, Debug)]
+    pub struct Stake {
+        pub is_initialized: bool,
+        pub owner: Pubkey,
+        pub amount: u64,
+        pub time_started: i64
+    }
+
+    impl Stake {
+        pub const LEN: usize = 1 + 32 + 8 + 8;
+    }
+
+    #[derive(BorshSerialize, BorshDeserialize, Debug)]
+    pub struct RewardsPool {
+        pub is_initialized: bool,
+        pub amount: u64,
+        pub airdrop_supply: u64,
+        pub airdrop_fee: u64,
+        pub total_coins_staked: u64,
+        pub total_stakes_count: u32
+    }
+
+    impl RewardsPool {
+        pub const LEN: usize = 1 + 8 * 4 + 4;
+    }
+
+    #[derive(BorshSerialize, BorshDeserialize, Debug)]
+    pub struct Ballot {
+        pub is_initialized: bool,
+        pub choices: [u32;5]
+    }
+
+    impl Ballot {
+        pub const LEN: usize = 1 + 4 * 5;
+    }
+}
@@ -0,0 +1,150 @@
+//! Zero-copy views of the pool and ballot accounts, which the program updates in place.
+//!
+//! Each view mirrors the Borsh layout in `state` byte for byte, with `u8` in place of `bool`,
+//! so it can be cast straight out of the account data instead of deserialized and written back.
+use crate::math::CheckedMath;
+use crate::state::{AccountState, Ballot, PoolBucket, RewardsPool};
+use bytemuck::Pod;
+use solana_program::{msg, program_error::ProgramError};
+use std::cell::RefMut;
+
+/// A Pod mirror of the account type `State`.
+pub trait ZeroCopy: Pod {
+    type State: AccountState;
+}
+
+//bytemuck_derive 1.0 emits padding and field checks next to each struct that are never called.
+//Each derive sits in its own module so the allow covers that struct's checks and nothing else.
+#[allow(dead_code)]
+mod header {
+    use bytemuck::{Pod, Zeroable};
+
+    #[repr(C, packed)]
+    #[derive(Clone, Copy, Pod, Zeroable)]
+    pub struct HeaderData {
+        pub discriminator: [u8; 8],
+        pub version: u8,
+    }
+}
+
+#[allow(dead_code)]
+mod rewards_pool {
+    use super::HeaderData;
+    use bytemuck::{Pod, Zeroable};
+    use solana_program::pubkey::Pubkey;
+
+    #[repr(C, packed)]
+    #[derive(Clone, Copy, Pod, Zeroable)]
+    pub struct RewardsPoolData {
+        pub header: HeaderData,
+        pub is_initialized: u8,
+        pub amount: u64,
+        pub airdrop_supply: u64,
+        pub airdrop_fee: u64,
+        pub total_coins_staked: u64,
+        pub total_stakes_count: u32,
+        pub airdrop_wallet_cap: u64,
+        pub airdrop_cooldown: i64,
+        pub airdrop_fee_mint: Pubkey,
+        pub airdrop_treasury: Pubkey,
+        pub airdrop_vesting_cliff: i64,
+        pub airdrop_vesting_duration: i64,
+        pub vesting_locked: u64,
+        pub paused: u8,
+    }
+}
+
+#[allow(dead_code)]
+mod ballot {
+    use super::HeaderData;
+    use bytemuck::{Pod, Zeroable};
+
+    #[repr(C, packed)]
+    #[derive(Clone, Copy, Pod, Zeroable)]
+    pub struct BallotData {
+        pub header: HeaderData,
+        pub is_initialized: u8,
+        pub choices: [u32; 5],
+    }
+}
+
+pub use ballot::BallotData;
+pub use header::HeaderData;
+pub use rewards_pool::RewardsPoolData;
+
+impl RewardsPoolData {
+    pub fn is_initialized(&self) -> bool {
+        self.is_initialized != 0
+    }
+
+    pub fn is_paused(&self, flag: u8) -> bool {
+        self.paused & flag != 0
+    }
+
+    pub fn bucket(&self, bucket: PoolBucket) -> u64 {
+        match bucket {
+            PoolBucket::Rewards => self.amount,
+            PoolBucket::Airdrop => self.airdrop_supply,
+        }
+    }
+
+    /// Packed fields can't be borrowed, so unlike `RewardsPool::bucket_mut` this writes by value.
+    pub fn set_bucket(&mut self, bucket: PoolBucket, amount: u64) {
+        match bucket {
+            PoolBucket::Rewards => self.amount = amount,
+            PoolBucket::Airdrop => self.airdrop_supply = amount,
+        }
+    }
+}
+
+impl ZeroCopy for RewardsPoolData {
+    type State = RewardsPool;
+}
+
+impl BallotData {
+    pub fn is_initialized(&self) -> bool {
+        self.is_initialized != 0
+    }
+
+    /// Counts one vote for `selection`, which must be one of the choices.
+    pub fn record_vote(&mut self, selection: u8) -> Result<(), ProgramError> {
+        //Packed fields can't be borrowed in place, update a copy of the tally.
+        let mut choices = self.choices;
+        let choice = choices
+            .get_mut(selection as usize)
+            .ok_or(ProgramError::InvalidInstructionData)?;
+        *choice = choice.try_add(1)?;
+        self.choices = choices;
+        Ok(())
+    }
+}
+
+impl ZeroCopy for BallotData {
+    type State = Ballot;
+}
+
+/// Casts account data to `T`, the data must be exactly `T::State::LEN` bytes.
+pub fn view<T: ZeroCopy>(data: &[u8]) -> Result<&T, ProgramError> {
+    check_len::<T>(data.len())?;
+    Ok(bytemuck::from_bytes(data))
+}
+
+/// Mutable form of `view` that keeps the account data borrowed for as long as the view lives.
+pub fn view_mut<'a, T: ZeroCopy>(
+    data: RefMut<'a, &mut [u8]>,
+) -> Result<RefMut<'a, T>, ProgramError> {
+    check_len::<T>(data.len())?;
+    Ok(RefMut::map(data, |data| bytemuck::from_bytes_mut(data)))
+}
+
+fn check_len<T: ZeroCopy>(len: usize) -> Result<(), ProgramError> {
+    if len != std::mem::size_of::<T>() || len != T::State::LEN {
+        msg!(
+            "Expected {} bytes of account data, got {}.",
+            T::State::LEN,
+            len
+        );
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(())
+}
@@ -0,0 +1,124 @@
+mod common;
+
+use common::*;
+use solana_program::{instruction::InstructionError, pubkey::Pubkey};
+use solana_program_test::tokio;
+use solana_sdk::{signature::Signer, transaction::TransactionError};
+use staking::{
+    instruction,
+    processor::BASE_COIN,
+    state::{AccountHeader, AccountState, RewardsPool, Stake},
+};
+
+//A pool some other program owns, and one still on the previous layout version.
+const FOREIGN_REWARDS: Pubkey = Pubkey::new_from_array([1; 32]);
+const STALE_REWARDS: Pubkey = Pubkey::new_from_array([2; 32]);
+
+async fn setup() -> Setup {
+    let mut builder = SetupBuilder::new(0);
+    let stake = Stake {
+        header: Stake::header(),
+        is_initialized: true,
+        owner: builder.admin.pubkey(),
+        amount: BASE_COIN,
+        time_started: 0,
+    };
+    add_program_account(
+        &mut builder.program_test,
+        &Pubkey::new_unique(),
+        &FOREIGN_REWARDS,
+        &rewards_pool(0, 0, 0),
+    );
+    add_program_account(
+        &mut builder.program_test,
+        &builder.program_id,
+        &STALE_REWARDS,
+        &RewardsPool {
+            header: AccountHeader {
+                version: 0,
+                ..RewardsPool::header()
+            },
+            ..rewards_pool(0, 0, 0)
+        },
+    );
+    SetupBuilder {
+        stake_state: Some(stake),
+        ..builder
+    }
+    .start()
+    .await
+}
+
+async fn change_fee(setup: &mut Setup, rewards: Pubkey) -> Result<(), TransactionError> {
+    let ix =
+        instruction::change_airdrop_fee(&setup.program_id, &setup.admin.pubkey(), &[], &rewards, 1);
+    send(&mut setup.context, ix, &[&setup.admin]).await
+}
+
+#[tokio::test]
+async fn test_rejects_other_account_types() {
+    let mut setup = setup().await;
+    let stake = setup.stake;
+
+    assert_eq!(
+        change_fee(&mut setup, stake).await.unwrap_err(),
+        error(InstructionError::InvalidAccountData)
+    );
+}
+
+#[tokio::test]
+async fn test_rejects_accounts_owned_by_other_programs() {
+    let mut setup = setup().await;
+
+    assert_eq!(
+        change_fee(&mut setup, FOREIGN_REWARDS).await.unwrap_err(),
+        error(InstructionError::IllegalOwner)
+    );
+
+    //CreateStake used to trust any rewards account.
+    let user_token = Pubkey::new_unique();
+    let ix = instruction::create_stake(
+        &setup.program_id,
+        &setup.admin.pubkey(),
+        &user_token,
+        &setup.stake,
+        &FOREIGN_REWARDS,
+        BASE_COIN,
+    );
+    assert_eq!(
+        send(&mut setup.context, ix, &[&setup.admin])
+            .await
+            .unwrap_err(),
+        error(InstructionError::IllegalOwner)
+    );
+}
+
+#[tokio::test]
+async fn test_rejects_outdated_versions() {
+    let mut setup = setup().await;
+
+    assert_eq!(
+        change_fee(&mut setup, STALE_REWARDS).await.unwrap_err(),
+        error(InstructionError::InvalidAccountData)
+    );
+}
+
+#[tokio::test]
+async fn test_rejects_readonly_state() {
+    let mut setup = setup().await;
+
+    let mut ix = instruction::change_airdrop_fee(
+        &setup.program_id,
+        &setup.admin.pubkey(),
+        &[],
+        &setup.rewards,
+        1,
+    );
+    ix.accounts[1].is_writable = false;
+    assert_eq!(
+        send(&mut setup.context, ix, &[&setup.admin])
+            .await
+            .unwrap_err(),
+        error(InstructionError::InvalidArgument)
+    );
+}
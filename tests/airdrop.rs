@@ -0,0 +1,473 @@
+mod common;
+
+use borsh::BorshSerialize;
+use common::*;
+use solana_program::{
+    instruction::InstructionError, pubkey::Pubkey, system_instruction::SystemError,
+};
+use solana_program_test::tokio;
+use solana_sdk::{
+    native_token::LAMPORTS_PER_SOL, signature::Signer, transaction::TransactionError,
+};
+use staking::{
+    get_airdrop_receipt_address, get_airdrop_vesting_address, instruction,
+    processor::{AIRDROP_MAX, BASE_COIN},
+    state::{AccountState, AirdropReceipt, RewardsPool, VestingSchedule},
+};
+
+const AIRDROP_FEE: u64 = LAMPORTS_PER_SOL / 10000;
+const AIRDROP_SUPPLY: u64 = 1_000_000 * BASE_COIN;
+
+async fn setup(pool: RewardsPool, user_lamports: u64) -> Setup {
+    setup_with(builder(pool, user_lamports), VestingSchedule::default()).await
+}
+
+fn builder(pool: RewardsPool, user_lamports: u64) -> SetupBuilder {
+    SetupBuilder {
+        pool: Some(pool),
+        user_lamports,
+        ..SetupBuilder::new(AIRDROP_SUPPLY)
+    }
+}
+
+/// Starts `builder` with `schedule` as the user's airdrop vesting schedule.
+async fn setup_with(mut builder: SetupBuilder, schedule: VestingSchedule) -> Setup {
+    let program_id = builder.program_id;
+    let user = builder.user.pubkey();
+    //Allocated up front, so the user's lamports only pay the airdrop fee.
+    add_raw_account(
+        &mut builder.program_test,
+        &program_id,
+        &get_airdrop_receipt_address(&program_id, &user),
+        vec![0; AirdropReceipt::LEN],
+    );
+    add_raw_account(
+        &mut builder.program_test,
+        &program_id,
+        &get_airdrop_vesting_address(&program_id, &user),
+        schedule.try_to_vec().unwrap(),
+    );
+    builder.start().await
+}
+
+async fn airdrop(setup: &mut Setup, amount: u64) -> Result<(), TransactionError> {
+    let ix = instruction::airdrop(
+        &setup.program_id,
+        &setup.user.pubkey(),
+        &setup.user_token,
+        &setup.rewards,
+        &admin_address(),
+        None,
+        amount,
+    );
+    send(&mut setup.context, ix, &[&setup.user]).await
+}
+
+/// Asserts that a rejected airdrop moved neither tokens nor lamports.
+async fn assert_untouched(setup: &mut Setup, user_lamports: u64) {
+    let banks_client = &mut setup.context.banks_client;
+    assert_eq!(
+        token_balance(banks_client, &pda_token_address(&setup.program_id)).await,
+        AIRDROP_SUPPLY
+    );
+    assert_eq!(token_balance(banks_client, &setup.user_token).await, 0);
+    assert_eq!(
+        lamports(banks_client, &setup.user.pubkey()).await,
+        user_lamports
+    );
+    assert_eq!(
+        lamports(banks_client, &admin_address()).await,
+        LAMPORTS_PER_SOL
+    );
+    let pool: RewardsPool = get_state(banks_client, &setup.rewards).await.unwrap();
+    assert_eq!(pool.airdrop_supply, AIRDROP_SUPPLY);
+}
+
+#[tokio::test]
+async fn test_airdrop_charges_fee_and_transfers_coins() {
+    let mut setup = setup(
+        rewards_pool(0, AIRDROP_SUPPLY, AIRDROP_FEE),
+        LAMPORTS_PER_SOL,
+    )
+    .await;
+
+    airdrop(&mut setup, 100 * BASE_COIN).await.unwrap();
+
+    let receipt_address = get_airdrop_receipt_address(&setup.program_id, &setup.user.pubkey());
+    let banks_client = &mut setup.context.banks_client;
+    assert_eq!(
+        token_balance(banks_client, &setup.user_token).await,
+        100 * BASE_COIN
+    );
+    assert_eq!(
+        lamports(banks_client, &setup.user.pubkey()).await,
+        LAMPORTS_PER_SOL - AIRDROP_FEE
+    );
+    assert_eq!(
+        lamports(banks_client, &admin_address()).await,
+        LAMPORTS_PER_SOL + AIRDROP_FEE
+    );
+    let pool: RewardsPool = get_state(banks_client, &setup.rewards).await.unwrap();
+    assert_eq!(pool.airdrop_supply, AIRDROP_SUPPLY - 100 * BASE_COIN);
+    let receipt: AirdropReceipt = get_state(banks_client, &receipt_address).await.unwrap();
+    assert!(receipt.is_initialized);
+    assert_eq!(receipt.owner, setup.user.pubkey());
+    assert_eq!(receipt.total_claimed, 100 * BASE_COIN);
+}
+
+#[tokio::test]
+async fn test_airdrop_creates_receipt_at_a_prefunded_address() {
+    let mut builder = builder(rewards_pool(0, AIRDROP_SUPPLY, 0), LAMPORTS_PER_SOL);
+    let receipt_address = get_airdrop_receipt_address(&builder.program_id, &builder.user.pubkey());
+    //Anyone can send lamports to the receipt address before the wallet first claims.
+    add_system_account(&mut builder.program_test, &receipt_address, 1);
+    let mut setup = builder.start().await;
+
+    airdrop(&mut setup, 100 * BASE_COIN).await.unwrap();
+
+    let banks_client = &mut setup.context.banks_client;
+    let rent = banks_client
+        .get_rent()
+        .await
+        .unwrap()
+        .minimum_balance(AirdropReceipt::LEN);
+    assert_eq!(lamports(banks_client, &receipt_address).await, rent);
+    assert_eq!(
+        lamports(banks_client, &setup.user.pubkey()).await,
+        LAMPORTS_PER_SOL - (rent - 1)
+    );
+    assert_eq!(
+        token_balance(banks_client, &setup.user_token).await,
+        100 * BASE_COIN
+    );
+    let receipt: AirdropReceipt = get_state(banks_client, &receipt_address).await.unwrap();
+    assert_eq!(receipt.owner, setup.user.pubkey());
+    assert_eq!(receipt.total_claimed, 100 * BASE_COIN);
+}
+
+#[tokio::test]
+async fn test_airdrop_requires_owner_signature() {
+    let mut setup = setup(
+        rewards_pool(0, AIRDROP_SUPPLY, AIRDROP_FEE),
+        LAMPORTS_PER_SOL,
+    )
+    .await;
+
+    let mut ix = instruction::airdrop(
+        &setup.program_id,
+        &setup.user.pubkey(),
+        &setup.user_token,
+        &setup.rewards,
+        &admin_address(),
+        None,
+        BASE_COIN,
+    );
+    ix.accounts[0].is_signer = false;
+
+    assert_eq!(
+        send(&mut setup.context, ix, &[]).await.unwrap_err(),
+        error(InstructionError::MissingRequiredSignature)
+    );
+    assert_untouched(&mut setup, LAMPORTS_PER_SOL).await;
+}
+
+#[tokio::test]
+async fn test_airdrop_rejects_wrong_treasury() {
+    let mut setup = setup(
+        rewards_pool(0, AIRDROP_SUPPLY, AIRDROP_FEE),
+        LAMPORTS_PER_SOL,
+    )
+    .await;
+
+    let mut ix = instruction::airdrop(
+        &setup.program_id,
+        &setup.user.pubkey(),
+        &setup.user_token,
+        &setup.rewards,
+        &admin_address(),
+        None,
+        BASE_COIN,
+    );
+    ix.accounts[6].pubkey = setup.user.pubkey();
+
+    assert_eq!(
+        send(&mut setup.context, ix, &[&setup.user])
+            .await
+            .unwrap_err(),
+        error(InstructionError::InvalidInstructionData)
+    );
+    assert_untouched(&mut setup, LAMPORTS_PER_SOL).await;
+}
+
+#[tokio::test]
+async fn test_airdrop_rejects_amount_over_max() {
+    let mut setup = setup(
+        rewards_pool(0, AIRDROP_SUPPLY, AIRDROP_FEE),
+        LAMPORTS_PER_SOL,
+    )
+    .await;
+
+    assert_eq!(
+        airdrop(&mut setup, AIRDROP_MAX + 1).await.unwrap_err(),
+        error(InstructionError::InvalidInstructionData)
+    );
+    assert_untouched(&mut setup, LAMPORTS_PER_SOL).await;
+}
+
+#[tokio::test]
+async fn test_airdrop_rejects_amount_over_supply() {
+    let mut setup = setup(rewards_pool(0, BASE_COIN, AIRDROP_FEE), LAMPORTS_PER_SOL).await;
+
+    assert_eq!(
+        airdrop(&mut setup, 2 * BASE_COIN).await.unwrap_err(),
+        error(InstructionError::InsufficientFunds)
+    );
+
+    let banks_client = &mut setup.context.banks_client;
+    assert_eq!(token_balance(banks_client, &setup.user_token).await, 0);
+    assert_eq!(
+        lamports(banks_client, &setup.user.pubkey()).await,
+        LAMPORTS_PER_SOL
+    );
+    assert_eq!(
+        lamports(banks_client, &admin_address()).await,
+        LAMPORTS_PER_SOL
+    );
+}
+
+#[tokio::test]
+async fn test_airdrop_rejects_unpaid_fee() {
+    let user_lamports = AIRDROP_FEE / 2;
+    let mut setup = setup(rewards_pool(0, AIRDROP_SUPPLY, AIRDROP_FEE), user_lamports).await;
+
+    assert_eq!(
+        airdrop(&mut setup, BASE_COIN).await.unwrap_err(),
+        error(InstructionError::Custom(
+            SystemError::ResultWithNegativeLamports as u32
+        ))
+    );
+    assert_untouched(&mut setup, user_lamports).await;
+}
+
+#[tokio::test]
+async fn test_airdrop_rejects_claim_during_cooldown() {
+    let mut pool = rewards_pool(0, AIRDROP_SUPPLY, AIRDROP_FEE);
+    pool.airdrop_cooldown = 3600;
+    let mut setup = setup(pool, LAMPORTS_PER_SOL).await;
+
+    airdrop(&mut setup, BASE_COIN).await.unwrap();
+    assert_eq!(
+        airdrop(&mut setup, 2 * BASE_COIN).await.unwrap_err(),
+        error(InstructionError::InvalidInstructionData)
+    );
+
+    let banks_client = &mut setup.context.banks_client;
+    assert_eq!(
+        token_balance(banks_client, &setup.user_token).await,
+        BASE_COIN
+    );
+    assert_eq!(
+        lamports(banks_client, &admin_address()).await,
+        LAMPORTS_PER_SOL + AIRDROP_FEE
+    );
+}
+
+#[tokio::test]
+async fn test_airdrop_rejects_claim_over_wallet_cap() {
+    let mut pool = rewards_pool(0, AIRDROP_SUPPLY, AIRDROP_FEE);
+    pool.airdrop_wallet_cap = 10 * BASE_COIN;
+    let mut setup = setup(pool, LAMPORTS_PER_SOL).await;
+
+    airdrop(&mut setup, 6 * BASE_COIN).await.unwrap();
+    assert_eq!(
+        airdrop(&mut setup, 5 * BASE_COIN).await.unwrap_err(),
+        error(InstructionError::InvalidInstructionData)
+    );
+    airdrop(&mut setup, 4 * BASE_COIN).await.unwrap();
+
+    let banks_client = &mut setup.context.banks_client;
+    assert_eq!(
+        token_balance(banks_client, &setup.user_token).await,
+        10 * BASE_COIN
+    );
+    let pool: RewardsPool = get_state(banks_client, &setup.rewards).await.unwrap();
+    assert_eq!(pool.airdrop_supply, AIRDROP_SUPPLY - 10 * BASE_COIN);
+}
+
+#[tokio::test]
+async fn test_airdrop_fee_in_kittycoin_funds_rewards_pool() {
+    let program_id = Pubkey::new_unique();
+    let mut pool = rewards_pool(0, AIRDROP_SUPPLY, BASE_COIN);
+    pool.airdrop_fee_mint = kittycoin_mint();
+    pool.airdrop_treasury = pda_token_address(&program_id);
+    let mut builder = SetupBuilder {
+        pool: Some(pool),
+        ..SetupBuilder::with_program_id(program_id, AIRDROP_SUPPLY)
+    };
+    let user_fee_token = Pubkey::new_unique();
+    add_token_account(
+        &mut builder.program_test,
+        &user_fee_token,
+        &builder.user.pubkey(),
+        10 * BASE_COIN,
+    );
+    let mut setup = setup_with(builder, VestingSchedule::default()).await;
+
+    let ix = instruction::airdrop(
+        &setup.program_id,
+        &setup.user.pubkey(),
+        &setup.user_token,
+        &setup.rewards,
+        &pda_token_address(&setup.program_id),
+        Some(&user_fee_token),
+        100 * BASE_COIN,
+    );
+    send(&mut setup.context, ix, &[&setup.user]).await.unwrap();
+
+    let banks_client = &mut setup.context.banks_client;
+    assert_eq!(
+        token_balance(banks_client, &setup.user_token).await,
+        100 * BASE_COIN
+    );
+    assert_eq!(
+        token_balance(banks_client, &user_fee_token).await,
+        9 * BASE_COIN
+    );
+    assert_eq!(
+        token_balance(banks_client, &pda_token_address(&setup.program_id)).await,
+        AIRDROP_SUPPLY - 99 * BASE_COIN
+    );
+    assert_eq!(
+        lamports(banks_client, &setup.user.pubkey()).await,
+        LAMPORTS_PER_SOL
+    );
+    let pool: RewardsPool = get_state(banks_client, &setup.rewards).await.unwrap();
+    assert_eq!(pool.amount, BASE_COIN);
+    assert_eq!(pool.airdrop_supply, AIRDROP_SUPPLY - 100 * BASE_COIN);
+}
+
+#[tokio::test]
+async fn test_airdrop_rejects_treasury_with_wrong_mint() {
+    let mut pool = rewards_pool(0, AIRDROP_SUPPLY, BASE_COIN);
+    pool.airdrop_fee_mint = Pubkey::new_unique();
+    pool.airdrop_treasury = admin_address();
+    let mut setup = setup(pool, LAMPORTS_PER_SOL).await;
+
+    assert!(airdrop(&mut setup, BASE_COIN).await.is_err());
+    assert_untouched(&mut setup, LAMPORTS_PER_SOL).await;
+}
+
+#[tokio::test]
+async fn test_vesting_airdrop_keeps_coins_in_vault() {
+    let mut pool = rewards_pool(0, AIRDROP_SUPPLY, AIRDROP_FEE);
+    pool.airdrop_vesting_cliff = 60;
+    pool.airdrop_vesting_duration = 3600;
+    let mut setup = setup(pool, LAMPORTS_PER_SOL).await;
+
+    airdrop(&mut setup, 1000 * BASE_COIN).await.unwrap();
+
+    let vesting_address = get_airdrop_vesting_address(&setup.program_id, &setup.user.pubkey());
+    let banks_client = &mut setup.context.banks_client;
+    assert_eq!(token_balance(banks_client, &setup.user_token).await, 0);
+    assert_eq!(
+        token_balance(banks_client, &pda_token_address(&setup.program_id)).await,
+        AIRDROP_SUPPLY
+    );
+    let pool: RewardsPool = get_state(banks_client, &setup.rewards).await.unwrap();
+    assert_eq!(pool.airdrop_supply, AIRDROP_SUPPLY - 1000 * BASE_COIN);
+    assert_eq!(pool.vesting_locked, 1000 * BASE_COIN);
+    let schedule: VestingSchedule = get_state(banks_client, &vesting_address).await.unwrap();
+    assert_eq!(schedule.beneficiary, setup.user.pubkey());
+    assert_eq!(schedule.total, 1000 * BASE_COIN);
+    assert_eq!(schedule.cliff_time, schedule.start_time + 60);
+    assert_eq!(schedule.end_time, schedule.start_time + 3600);
+
+    let release = instruction::release_vested(
+        &setup.program_id,
+        &setup.user.pubkey(),
+        &setup.user_token,
+        &setup.rewards,
+    );
+    assert_eq!(
+        send(&mut setup.context, release, &[&setup.user])
+            .await
+            .unwrap_err(),
+        error(InstructionError::InvalidInstructionData)
+    );
+    assert_eq!(
+        airdrop(&mut setup, BASE_COIN).await.unwrap_err(),
+        error(InstructionError::InvalidInstructionData)
+    );
+}
+
+#[tokio::test]
+async fn test_vesting_airdrop_creates_schedule_at_a_prefunded_address() {
+    let mut pool = rewards_pool(0, AIRDROP_SUPPLY, 0);
+    pool.airdrop_vesting_duration = 3600;
+    let mut builder = builder(pool, LAMPORTS_PER_SOL);
+    let vesting_address = get_airdrop_vesting_address(&builder.program_id, &builder.user.pubkey());
+    //Anyone can send lamports to the schedule address before the wallet first claims.
+    add_system_account(&mut builder.program_test, &vesting_address, 1);
+    let mut setup = builder.start().await;
+
+    airdrop(&mut setup, 1000 * BASE_COIN).await.unwrap();
+
+    let banks_client = &mut setup.context.banks_client;
+    let rent = banks_client.get_rent().await.unwrap();
+    assert_eq!(
+        lamports(banks_client, &vesting_address).await,
+        rent.minimum_balance(VestingSchedule::LEN)
+    );
+    let schedule: VestingSchedule = get_state(banks_client, &vesting_address).await.unwrap();
+    assert_eq!(schedule.beneficiary, setup.user.pubkey());
+    assert_eq!(schedule.total, 1000 * BASE_COIN);
+    let pool: RewardsPool = get_state(banks_client, &setup.rewards).await.unwrap();
+    assert_eq!(pool.vesting_locked, 1000 * BASE_COIN);
+}
+
+#[tokio::test]
+async fn test_release_vested_unlocks_linear_portion() {
+    let mut pool = rewards_pool(0, AIRDROP_SUPPLY - 1000 * BASE_COIN, AIRDROP_FEE);
+    pool.vesting_locked = 1000 * BASE_COIN;
+    let start_time = 1_700_000_000;
+    let builder = builder(pool, LAMPORTS_PER_SOL);
+    let schedule = VestingSchedule {
+        header: VestingSchedule::header(),
+        is_initialized: true,
+        beneficiary: builder.user.pubkey(),
+        start_time,
+        cliff_time: start_time + 60,
+        end_time: start_time + 3600,
+        total: 1000 * BASE_COIN,
+        released: 0,
+    };
+    let mut setup = setup_with(builder, schedule).await;
+    //Halfway from start to end.
+    stubs::set_unix_timestamp(&setup.program_id, start_time + 1800);
+
+    let release = instruction::release_vested(
+        &setup.program_id,
+        &setup.user.pubkey(),
+        &setup.user_token,
+        &setup.rewards,
+    );
+    send(&mut setup.context, release, &[&setup.user])
+        .await
+        .unwrap();
+
+    let vesting_address = get_airdrop_vesting_address(&setup.program_id, &setup.user.pubkey());
+    let banks_client = &mut setup.context.banks_client;
+    let schedule: VestingSchedule = get_state(banks_client, &vesting_address).await.unwrap();
+    let released = 500 * BASE_COIN;
+    assert_eq!(schedule.released, released);
+    assert_eq!(
+        token_balance(banks_client, &setup.user_token).await,
+        released
+    );
+    assert_eq!(
+        token_balance(banks_client, &pda_token_address(&setup.program_id)).await,
+        AIRDROP_SUPPLY - released
+    );
+    let pool: RewardsPool = get_state(banks_client, &setup.rewards).await.unwrap();
+    assert_eq!(pool.vesting_locked, 1000 * BASE_COIN - released);
+}
@@ -0,0 +1,705 @@
+mod common;
+
+use common::*;
+use solana_program::{instruction::InstructionError, pubkey::Pubkey};
+use solana_program_test::tokio;
+use solana_sdk::{
+    native_token::LAMPORTS_PER_SOL,
+    signature::{Keypair, Signer},
+    system_instruction,
+    transaction::{Transaction, TransactionError},
+};
+use staking::{
+    get_authority_address, instruction,
+    processor::BASE_COIN,
+    state::{AccountState, Authority, AuthorityType, Ballot, Multisig, RewardsPool, MAX_SIGNERS},
+};
+
+/// Keys holding the roles the pool admin does not.
+struct Roles {
+    fee_manager: Keypair,
+    treasury: Keypair,
+    multisig: Pubkey,
+    team: Vec<Keypair>,
+}
+
+async fn setup() -> (Setup, Roles) {
+    let roles = roles();
+    let setup = builder(&roles).start().await;
+    (setup, roles)
+}
+
+fn roles() -> Roles {
+    Roles {
+        fee_manager: Keypair::new(),
+        treasury: Keypair::new(),
+        multisig: Pubkey::new_unique(),
+        team: vec![Keypair::new(), Keypair::new(), Keypair::new()],
+    }
+}
+
+fn builder(roles: &Roles) -> SetupBuilder {
+    let mut builder = SetupBuilder::new(0);
+    //2 of 3 team multisig, holds no role until one is assigned to it.
+    let mut signers = [Pubkey::default(); MAX_SIGNERS];
+    for (i, member) in roles.team.iter().enumerate() {
+        signers[i] = member.pubkey();
+    }
+    add_program_account(
+        &mut builder.program_test,
+        &builder.program_id,
+        &roles.multisig,
+        &Multisig {
+            header: Multisig::header(),
+            is_initialized: true,
+            m: 2,
+            n: 3,
+            signers,
+        },
+    );
+    SetupBuilder {
+        authority: Authority {
+            fee_manager: roles.fee_manager.pubkey(),
+            treasury: roles.treasury.pubkey(),
+            guardian: Pubkey::default(),
+            ..builder.authority
+        },
+        ..builder
+    }
+}
+
+#[tokio::test]
+async fn test_fee_manager_changes_airdrop_fee() {
+    let (mut setup, roles) = setup().await;
+
+    let ix = instruction::change_airdrop_fee(
+        &setup.program_id,
+        &roles.fee_manager.pubkey(),
+        &[],
+        &setup.rewards,
+        LAMPORTS_PER_SOL,
+    );
+    send(&mut setup.context, ix, &[&roles.fee_manager])
+        .await
+        .unwrap();
+
+    let pool: RewardsPool = get_state(&mut setup.context.banks_client, &setup.rewards)
+        .await
+        .unwrap();
+    assert_eq!(pool.airdrop_fee, LAMPORTS_PER_SOL);
+}
+
+#[tokio::test]
+async fn test_roles_are_not_interchangeable() {
+    let (mut setup, roles) = setup().await;
+
+    let ix = instruction::change_airdrop_fee(
+        &setup.program_id,
+        &setup.admin.pubkey(),
+        &[],
+        &setup.rewards,
+        LAMPORTS_PER_SOL,
+    );
+    assert_eq!(
+        send(&mut setup.context, ix, &[&setup.admin])
+            .await
+            .unwrap_err(),
+        rejected()
+    );
+
+    let ix = instruction::set_airdrop_treasury(
+        &setup.program_id,
+        &roles.fee_manager.pubkey(),
+        &[],
+        &setup.rewards,
+        &Pubkey::default(),
+        &roles.fee_manager.pubkey(),
+    );
+    assert_eq!(
+        send(&mut setup.context, ix, &[&roles.fee_manager])
+            .await
+            .unwrap_err(),
+        rejected()
+    );
+
+    let ix = instruction::set_airdrop_limits(
+        &setup.program_id,
+        &roles.treasury.pubkey(),
+        &[],
+        &setup.rewards,
+        0,
+        60,
+    );
+    assert_eq!(
+        send(&mut setup.context, ix, &[&roles.treasury])
+            .await
+            .unwrap_err(),
+        rejected()
+    );
+}
+
+#[tokio::test]
+async fn test_treasury_redirects_airdrop_fees() {
+    let (mut setup, roles) = setup().await;
+    let new_treasury = Pubkey::new_unique();
+
+    let ix = instruction::set_airdrop_treasury(
+        &setup.program_id,
+        &roles.treasury.pubkey(),
+        &[],
+        &setup.rewards,
+        &Pubkey::default(),
+        &new_treasury,
+    );
+    send(&mut setup.context, ix, &[&roles.treasury])
+        .await
+        .unwrap();
+
+    let pool: RewardsPool = get_state(&mut setup.context.banks_client, &setup.rewards)
+        .await
+        .unwrap();
+    assert_eq!(pool.airdrop_treasury, new_treasury);
+}
+
+#[tokio::test]
+async fn test_pool_admin_reassigns_any_role() {
+    let (mut setup, roles) = setup().await;
+    let new_fee_manager = Keypair::new();
+
+    let ix = instruction::set_authority(
+        &setup.program_id,
+        &setup.admin.pubkey(),
+        &[],
+        AuthorityType::FeeManager,
+        &new_fee_manager.pubkey(),
+    );
+    send(&mut setup.context, ix, &[&setup.admin]).await.unwrap();
+
+    let ix = instruction::change_airdrop_fee(
+        &setup.program_id,
+        &new_fee_manager.pubkey(),
+        &[],
+        &setup.rewards,
+        1,
+    );
+    send(&mut setup.context, ix, &[&new_fee_manager])
+        .await
+        .unwrap();
+
+    let ix = instruction::change_airdrop_fee(
+        &setup.program_id,
+        &roles.fee_manager.pubkey(),
+        &[],
+        &setup.rewards,
+        2,
+    );
+    assert_eq!(
+        send(&mut setup.context, ix, &[&roles.fee_manager])
+            .await
+            .unwrap_err(),
+        rejected()
+    );
+}
+
+#[tokio::test]
+async fn test_role_holder_hands_off_own_role_only() {
+    let (mut setup, roles) = setup().await;
+    let new_treasury = Keypair::new();
+
+    let ix = instruction::set_authority(
+        &setup.program_id,
+        &roles.treasury.pubkey(),
+        &[],
+        AuthorityType::Treasury,
+        &new_treasury.pubkey(),
+    );
+    send(&mut setup.context, ix, &[&roles.treasury])
+        .await
+        .unwrap();
+
+    let ix = instruction::set_authority(
+        &setup.program_id,
+        &roles.fee_manager.pubkey(),
+        &[],
+        AuthorityType::PoolAdmin,
+        &roles.fee_manager.pubkey(),
+    );
+    assert_eq!(
+        send(&mut setup.context, ix, &[&roles.fee_manager])
+            .await
+            .unwrap_err(),
+        rejected()
+    );
+
+    let authority: Authority = get_state(
+        &mut setup.context.banks_client,
+        &get_authority_address(&setup.program_id),
+    )
+    .await
+    .unwrap();
+    assert_eq!(authority.treasury, new_treasury.pubkey());
+    assert_eq!(authority.pool_admin, setup.admin.pubkey());
+}
+
+#[tokio::test]
+async fn test_pool_admin_moves_only_through_propose_and_accept() {
+    let (mut setup, roles) = setup().await;
+    let new_admin = Keypair::new();
+
+    let ix = instruction::set_authority(
+        &setup.program_id,
+        &setup.admin.pubkey(),
+        &[],
+        AuthorityType::PoolAdmin,
+        &new_admin.pubkey(),
+    );
+    assert_eq!(
+        send(&mut setup.context, ix, &[&setup.admin])
+            .await
+            .unwrap_err(),
+        rejected()
+    );
+
+    let ix = instruction::propose_admin(
+        &setup.program_id,
+        &setup.admin.pubkey(),
+        &[],
+        &new_admin.pubkey(),
+    );
+    send(&mut setup.context, ix, &[&setup.admin]).await.unwrap();
+
+    //Only the proposed key can accept.
+    let ix = instruction::accept_admin(&setup.program_id, &roles.fee_manager.pubkey(), &[]);
+    assert_eq!(
+        send(&mut setup.context, ix, &[&roles.fee_manager])
+            .await
+            .unwrap_err(),
+        rejected()
+    );
+
+    let ix = instruction::accept_admin(&setup.program_id, &new_admin.pubkey(), &[]);
+    send(&mut setup.context, ix, &[&new_admin]).await.unwrap();
+
+    let authority: Authority = get_state(
+        &mut setup.context.banks_client,
+        &get_authority_address(&setup.program_id),
+    )
+    .await
+    .unwrap();
+    assert_eq!(authority.pool_admin, new_admin.pubkey());
+    assert_eq!(authority.pending_admin, Pubkey::default());
+}
+
+#[tokio::test]
+async fn test_renounce_admin_freezes_parameters() {
+    let (mut setup, roles) = setup().await;
+
+    let ix = instruction::renounce_admin(&setup.program_id, &setup.admin.pubkey(), &[]);
+    send(&mut setup.context, ix, &[&setup.admin]).await.unwrap();
+
+    let authority: Authority = get_state(
+        &mut setup.context.banks_client,
+        &get_authority_address(&setup.program_id),
+    )
+    .await
+    .unwrap();
+    assert!(authority.renounced);
+    assert_eq!(authority.pool_admin, Pubkey::default());
+    assert_eq!(authority.fee_manager, Pubkey::default());
+    assert_eq!(authority.treasury, Pubkey::default());
+    //Ballots can still be started.
+    assert_eq!(authority.ballot_creator, setup.admin.pubkey());
+
+    let ix = instruction::change_airdrop_fee(
+        &setup.program_id,
+        &roles.fee_manager.pubkey(),
+        &[],
+        &setup.rewards,
+        1,
+    );
+    assert_eq!(
+        send(&mut setup.context, ix, &[&roles.fee_manager])
+            .await
+            .unwrap_err(),
+        rejected()
+    );
+
+    let ix = instruction::set_authority(
+        &setup.program_id,
+        &roles.treasury.pubkey(),
+        &[],
+        AuthorityType::Treasury,
+        &roles.treasury.pubkey(),
+    );
+    assert_eq!(
+        send(&mut setup.context, ix, &[&roles.treasury])
+            .await
+            .unwrap_err(),
+        rejected()
+    );
+}
+
+async fn assign_fee_manager_to_multisig(setup: &mut Setup, roles: &Roles) {
+    let ix = instruction::set_authority(
+        &setup.program_id,
+        &setup.admin.pubkey(),
+        &[],
+        AuthorityType::FeeManager,
+        &roles.multisig,
+    );
+    send(&mut setup.context, ix, &[&setup.admin]).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_multisig_changes_fee_with_enough_signers() {
+    let (mut setup, roles) = setup().await;
+    assign_fee_manager_to_multisig(&mut setup, &roles).await;
+
+    let ix = instruction::change_airdrop_fee(
+        &setup.program_id,
+        &roles.multisig,
+        &[&roles.team[0].pubkey(), &roles.team[2].pubkey()],
+        &setup.rewards,
+        LAMPORTS_PER_SOL,
+    );
+    send(&mut setup.context, ix, &[&roles.team[0], &roles.team[2]])
+        .await
+        .unwrap();
+
+    let pool: RewardsPool = get_state(&mut setup.context.banks_client, &setup.rewards)
+        .await
+        .unwrap();
+    assert_eq!(pool.airdrop_fee, LAMPORTS_PER_SOL);
+}
+
+#[tokio::test]
+async fn test_multisig_rejects_below_threshold() {
+    let (mut setup, roles) = setup().await;
+    assign_fee_manager_to_multisig(&mut setup, &roles).await;
+
+    let ix = instruction::change_airdrop_fee(
+        &setup.program_id,
+        &roles.multisig,
+        &[&roles.team[1].pubkey()],
+        &setup.rewards,
+        LAMPORTS_PER_SOL,
+    );
+    assert_eq!(
+        send(&mut setup.context, ix, &[&roles.team[1]])
+            .await
+            .unwrap_err(),
+        error(InstructionError::MissingRequiredSignature)
+    );
+
+    //The same key passed twice only counts once.
+    let ix = instruction::change_airdrop_fee(
+        &setup.program_id,
+        &roles.multisig,
+        &[&roles.team[1].pubkey(), &roles.team[1].pubkey()],
+        &setup.rewards,
+        LAMPORTS_PER_SOL,
+    );
+    assert_eq!(
+        send(&mut setup.context, ix, &[&roles.team[1]])
+            .await
+            .unwrap_err(),
+        error(InstructionError::MissingRequiredSignature)
+    );
+
+    //Outsiders do not count toward the threshold.
+    let outsider = Keypair::new();
+    let ix = instruction::change_airdrop_fee(
+        &setup.program_id,
+        &roles.multisig,
+        &[&roles.team[1].pubkey(), &outsider.pubkey()],
+        &setup.rewards,
+        LAMPORTS_PER_SOL,
+    );
+    assert_eq!(
+        send(&mut setup.context, ix, &[&roles.team[1], &outsider])
+            .await
+            .unwrap_err(),
+        error(InstructionError::MissingRequiredSignature)
+    );
+}
+
+#[tokio::test]
+async fn test_multisig_pool_admin_initializes_pool() {
+    let roles = roles();
+    let builder = builder(&roles);
+    let mut setup = SetupBuilder {
+        pool: None,
+        authority: Authority {
+            pool_admin: roles.multisig,
+            ..builder.authority
+        },
+        admin_coins: BASE_COIN,
+        ..builder
+    }
+    .start()
+    .await;
+
+    let ix = instruction::add_to_rewards_pool(
+        &setup.program_id,
+        &setup.admin.pubkey(),
+        &setup.admin_token,
+        &setup.rewards,
+        BASE_COIN,
+        false,
+    );
+    //The donator alone no longer holds the role.
+    assert_eq!(
+        send(&mut setup.context, ix.clone(), &[&setup.admin])
+            .await
+            .unwrap_err(),
+        rejected()
+    );
+
+    let ix = instruction::with_multisig_authority(
+        ix,
+        &roles.multisig,
+        &[&roles.team[0].pubkey(), &roles.team[2].pubkey()],
+    );
+    send(
+        &mut setup.context,
+        ix,
+        &[&setup.admin, &roles.team[0], &roles.team[2]],
+    )
+    .await
+    .unwrap();
+
+    let pool: RewardsPool = get_state(&mut setup.context.banks_client, &setup.rewards)
+        .await
+        .unwrap();
+    assert!(pool.is_initialized);
+    assert_eq!(pool.amount, BASE_COIN);
+    assert_eq!(pool.airdrop_treasury, roles.treasury.pubkey());
+}
+
+const BALLOT: Pubkey = Pubkey::new_from_array([3; 32]);
+const VOTING_FEE: u64 = 1000 * BASE_COIN;
+
+/// The multisig holds the ballot creator role and the admin votes on a fresh ballot.
+async fn setup_ballot(roles: &Roles) -> Setup {
+    let mut builder = builder(roles);
+    add_raw_account(
+        &mut builder.program_test,
+        &builder.program_id,
+        &BALLOT,
+        vec![0; Ballot::LEN],
+    );
+    SetupBuilder {
+        authority: Authority {
+            ballot_creator: roles.multisig,
+            ..builder.authority
+        },
+        admin_coins: VOTING_FEE,
+        ..builder
+    }
+    .start()
+    .await
+}
+
+/// Votes on the fresh ballot with `team` approving for the multisig.
+async fn start_ballot(
+    setup: &mut Setup,
+    roles: &Roles,
+    team: &[&Keypair],
+) -> Result<(), TransactionError> {
+    let ix = instruction::vote(
+        &setup.program_id,
+        &setup.admin.pubkey(),
+        &setup.admin_token,
+        &BALLOT,
+        &setup.rewards,
+        0,
+    );
+    let keys: Vec<Pubkey> = team.iter().map(|member| member.pubkey()).collect();
+    let keys: Vec<&Pubkey> = keys.iter().collect();
+    let ix = instruction::with_multisig_authority(ix, &roles.multisig, &keys);
+    let admin = Keypair::from_bytes(&setup.admin.to_bytes()).unwrap();
+    let mut signers = vec![&admin];
+    signers.extend_from_slice(team);
+    send(&mut setup.context, ix, &signers).await
+}
+
+#[tokio::test]
+async fn test_multisig_ballot_creator_starts_ballot_with_enough_signers() {
+    let roles = roles();
+    let mut setup = setup_ballot(&roles).await;
+
+    start_ballot(&mut setup, &roles, &[&roles.team[0], &roles.team[1]])
+        .await
+        .unwrap();
+
+    let ballot: Ballot = get_state(&mut setup.context.banks_client, &BALLOT)
+        .await
+        .unwrap();
+    assert!(ballot.is_initialized);
+    assert_eq!(ballot.choices, [1, 0, 0, 0, 0]);
+}
+
+#[tokio::test]
+async fn test_multisig_ballot_creator_rejects_below_threshold() {
+    let roles = roles();
+    let mut setup = setup_ballot(&roles).await;
+
+    assert_eq!(
+        start_ballot(&mut setup, &roles, &[&roles.team[1]])
+            .await
+            .unwrap_err(),
+        error(InstructionError::MissingRequiredSignature)
+    );
+
+    //Without the multisig the voter has to be the ballot creator.
+    let ix = instruction::vote(
+        &setup.program_id,
+        &setup.admin.pubkey(),
+        &setup.admin_token,
+        &BALLOT,
+        &setup.rewards,
+        0,
+    );
+    assert_eq!(
+        send(&mut setup.context, ix, &[&setup.admin])
+            .await
+            .unwrap_err(),
+        rejected()
+    );
+}
+
+#[tokio::test]
+async fn test_initialize_multisig() {
+    let (mut setup, roles) = setup().await;
+    let multisig = Keypair::new();
+    let rent = setup.context.banks_client.get_rent().await.unwrap();
+
+    let tx = Transaction::new_signed_with_payer(
+        &[
+            system_instruction::create_account(
+                &setup.context.payer.pubkey(),
+                &multisig.pubkey(),
+                rent.minimum_balance(Multisig::LEN),
+                Multisig::LEN as u64,
+                &setup.program_id,
+            ),
+            instruction::initialize_multisig(
+                &setup.program_id,
+                &multisig.pubkey(),
+                &[&roles.team[0].pubkey(), &roles.team[1].pubkey()],
+                2,
+            ),
+        ],
+        Some(&setup.context.payer.pubkey()),
+        &[&setup.context.payer, &multisig],
+        setup.context.last_blockhash,
+    );
+    setup
+        .context
+        .banks_client
+        .process_transaction(tx)
+        .await
+        .unwrap();
+
+    let state: Multisig = get_state(&mut setup.context.banks_client, &multisig.pubkey())
+        .await
+        .unwrap();
+    assert!(state.is_initialized);
+    assert_eq!((state.m, state.n), (2, 2));
+    assert_eq!(state.signers[1], roles.team[1].pubkey());
+
+    //An initialized multisig cannot be overwritten.
+    let ix = instruction::initialize_multisig(
+        &setup.program_id,
+        &roles.multisig,
+        &[&roles.team[0].pubkey()],
+        2,
+    );
+    assert!(send(&mut setup.context, ix, &[]).await.is_err());
+}
+
+#[tokio::test]
+async fn test_pool_admin_sets_airdrop_vesting() {
+    let (mut setup, roles) = setup().await;
+
+    let set_vesting = |setup: &Setup, admin: &Keypair, cliff, duration| {
+        instruction::set_airdrop_vesting(
+            &setup.program_id,
+            &admin.pubkey(),
+            &[],
+            &setup.rewards,
+            cliff,
+            duration,
+        )
+    };
+    let ix = set_vesting(&setup, &roles.fee_manager, 60, 3600);
+    assert_eq!(
+        send(&mut setup.context, ix, &[&roles.fee_manager])
+            .await
+            .unwrap_err(),
+        rejected()
+    );
+    for (cliff, duration) in [(-1, 3600), (60, -1), (3600, 60)] {
+        let ix = set_vesting(&setup, &setup.admin, cliff, duration);
+        assert_eq!(
+            send(&mut setup.context, ix, &[&setup.admin])
+                .await
+                .unwrap_err(),
+            rejected()
+        );
+    }
+
+    let ix = set_vesting(&setup, &setup.admin, 60, 3600);
+    send(&mut setup.context, ix, &[&setup.admin]).await.unwrap();
+    let pool: RewardsPool = get_state(&mut setup.context.banks_client, &setup.rewards)
+        .await
+        .unwrap();
+    assert_eq!(pool.airdrop_vesting_cliff, 60);
+    assert_eq!(pool.airdrop_vesting_duration, 3600);
+}
+
+#[tokio::test]
+async fn test_initialize_authority_requires_deploy_key() {
+    let (mut setup, _roles) = setup().await;
+    let impostor = Keypair::new();
+
+    let mut ix = instruction::initialize_authority(&setup.program_id);
+    ix.accounts[0].pubkey = impostor.pubkey();
+    assert_eq!(
+        send(&mut setup.context, ix, &[&impostor])
+            .await
+            .unwrap_err(),
+        rejected()
+    );
+}
+
+#[tokio::test]
+async fn test_initialize_authority_at_a_prefunded_address() {
+    let program_id = Pubkey::new_unique();
+    let authority = get_authority_address(&program_id);
+    let mut program_test = program_test(&program_id, &Pubkey::new_unique(), 0);
+    //Anyone can send lamports to the address before the deploy key gets to initialize it.
+    add_system_account(&mut program_test, &authority, 1);
+    let mut context = start_context(program_test).await;
+    stubs::sign_as(&program_id, &admin_address());
+
+    let mut ix = instruction::initialize_authority(&program_id);
+    ix.accounts[0].is_signer = false;
+    send(&mut context, ix, &[]).await.unwrap();
+
+    let rent = context.banks_client.get_rent().await.unwrap();
+    let account = context
+        .banks_client
+        .get_account(authority)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(account.owner, program_id);
+    assert_eq!(account.lamports, rent.minimum_balance(Authority::LEN));
+    let authority: Authority = get_state(&mut context.banks_client, &authority)
+        .await
+        .unwrap();
+    assert!(authority.is_initialized);
+    assert_eq!(authority.pool_admin, admin_address());
+    assert_eq!(authority.guardian, admin_address());
+}
@@ -0,0 +1,390 @@
+#![allow(dead_code)]
+
+pub mod stubs;
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::instruction::{Instruction, InstructionError};
+use solana_program::{program_option::COption, program_pack::Pack, pubkey::Pubkey, rent::Rent};
+use solana_program_test::{processor, BanksClient, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    account::Account,
+    native_token::LAMPORTS_PER_SOL,
+    process_instruction::ProcessInstructionWithContext,
+    signature::{Keypair, Signer},
+    system_program,
+    transaction::{Transaction, TransactionError},
+};
+use staking::{
+    get_authority_address, get_pda_address,
+    processor::{ADMIN_ADDRESS, KITTYCOIN_MINT},
+    state::{AccountState, Authority, RewardsPool, Stake},
+};
+
+pub fn kittycoin_mint() -> Pubkey {
+    Pubkey::new_from_array(KITTYCOIN_MINT)
+}
+
+pub fn admin_address() -> Pubkey {
+    Pubkey::new_from_array(ADMIN_ADDRESS)
+}
+
+pub fn pda_token_address(program_id: &Pubkey) -> Pubkey {
+    spl_associated_token_account::get_associated_token_address(
+        &get_pda_address(program_id),
+        &kittycoin_mint(),
+    )
+}
+
+/// Program test with the Kittycoin mint, a vault holding `vault_amount` and a funded treasury.
+/// The program runs natively under the stubs, also under `cargo test-bpf`.
+pub fn program_test(
+    program_id: &Pubkey,
+    mint_authority: &Pubkey,
+    vault_amount: u64,
+) -> ProgramTest {
+    program_test_of(
+        program_id,
+        Some(stubs::process_builtin),
+        mint_authority,
+        vault_amount,
+    )
+}
+
+/// Same as `program_test` with the BPF build of the program that `cargo test-bpf` makes, for
+/// what the stubs cannot run.
+pub fn bpf_program_test(
+    program_id: &Pubkey,
+    mint_authority: &Pubkey,
+    vault_amount: u64,
+) -> ProgramTest {
+    program_test_of(program_id, None, mint_authority, vault_amount)
+}
+
+fn program_test_of(
+    program_id: &Pubkey,
+    process_instruction: Option<ProcessInstructionWithContext>,
+    mint_authority: &Pubkey,
+    vault_amount: u64,
+) -> ProgramTest {
+    let mut program_test = ProgramTest::default();
+    program_test.prefer_bpf(process_instruction.is_none());
+    program_test.add_program("staking", *program_id, process_instruction);
+    program_test.prefer_bpf(false);
+    program_test.add_program(
+        "spl_token",
+        spl_token::id(),
+        processor!(spl_token::processor::Processor::process),
+    );
+
+    let mut data = vec![0; spl_token::state::Mint::LEN];
+    spl_token::state::Mint {
+        mint_authority: COption::Some(*mint_authority),
+        supply: 0,
+        decimals: 6,
+        is_initialized: true,
+        freeze_authority: COption::None,
+    }
+    .pack_into_slice(&mut data);
+    program_test.add_account(
+        kittycoin_mint(),
+        Account {
+            lamports: LAMPORTS_PER_SOL,
+            data,
+            owner: spl_token::id(),
+            ..Account::default()
+        },
+    );
+
+    add_token_account(
+        &mut program_test,
+        &pda_token_address(program_id),
+        &get_pda_address(program_id),
+        vault_amount,
+    );
+    add_system_account(&mut program_test, &admin_address(), LAMPORTS_PER_SOL);
+    program_test
+}
+
+/// Starts `program_test` with the return data and clock stubs in place.
+pub async fn start_context(program_test: ProgramTest) -> ProgramTestContext {
+    let context = program_test.start_with_context().await;
+    stubs::install();
+    context
+}
+
+/// The accounts most tests work with, see `SetupBuilder`.
+pub struct Setup {
+    pub context: ProgramTestContext,
+    pub program_id: Pubkey,
+    pub rewards: Pubkey,
+    pub admin: Keypair,
+    pub admin_token: Pubkey,
+    pub user: Keypair,
+    pub user_token: Pubkey,
+    pub stake: Pubkey,
+}
+
+/// A fresh program with a pool, a stake account, an admin and a user, each with SOL and a token
+/// account. Tests change the defaults with struct update syntax and add their own accounts to
+/// `program_test` before `start`.
+pub struct SetupBuilder {
+    pub program_test: ProgramTest,
+    pub program_id: Pubkey,
+    pub rewards: Pubkey,
+    pub pool: Option<RewardsPool>, //`None` leaves a zeroed account for the admin to initialize.
+    pub authority: Authority,
+    pub admin: Keypair,
+    pub admin_token: Pubkey,
+    pub admin_coins: u64,
+    pub user: Keypair,
+    pub user_token: Pubkey,
+    pub user_coins: u64,
+    pub user_lamports: u64,
+    pub stake: Pubkey,
+    pub stake_state: Option<Stake>, //`None` leaves a zeroed account for `CreateStake`.
+}
+
+impl SetupBuilder {
+    /// An empty pool, `vault_amount` in the vault and every role held by the admin.
+    pub fn new(vault_amount: u64) -> SetupBuilder {
+        SetupBuilder::with_program_id(Pubkey::new_unique(), vault_amount)
+    }
+
+    /// Same as `new` for tests that derive addresses from the program id up front.
+    pub fn with_program_id(program_id: Pubkey, vault_amount: u64) -> SetupBuilder {
+        let admin = Keypair::new();
+        SetupBuilder {
+            program_test: program_test(&program_id, &Pubkey::new_unique(), vault_amount),
+            program_id,
+            rewards: Pubkey::new_unique(),
+            pool: Some(rewards_pool(0, 0, 0)),
+            authority: authority(&admin.pubkey()),
+            admin,
+            admin_token: Pubkey::new_unique(),
+            admin_coins: 0,
+            user: Keypair::new(),
+            user_token: Pubkey::new_unique(),
+            user_coins: 0,
+            user_lamports: LAMPORTS_PER_SOL,
+            stake: Pubkey::new_unique(),
+            stake_state: None,
+        }
+    }
+
+    pub async fn start(mut self) -> Setup {
+        let program_test = &mut self.program_test;
+        let program_id = &self.program_id;
+        match &self.pool {
+            Some(pool) => add_program_account(program_test, program_id, &self.rewards, pool),
+            None => add_raw_account(
+                program_test,
+                program_id,
+                &self.rewards,
+                vec![0; RewardsPool::LEN],
+            ),
+        }
+        match &self.stake_state {
+            Some(stake) => add_program_account(program_test, program_id, &self.stake, stake),
+            None => add_raw_account(program_test, program_id, &self.stake, vec![0; Stake::LEN]),
+        }
+        add_authority(program_test, program_id, &self.authority);
+        add_system_account(program_test, &self.admin.pubkey(), LAMPORTS_PER_SOL);
+        add_token_account(
+            program_test,
+            &self.admin_token,
+            &self.admin.pubkey(),
+            self.admin_coins,
+        );
+        add_system_account(program_test, &self.user.pubkey(), self.user_lamports);
+        add_token_account(
+            program_test,
+            &self.user_token,
+            &self.user.pubkey(),
+            self.user_coins,
+        );
+
+        Setup {
+            context: start_context(self.program_test).await,
+            program_id: self.program_id,
+            rewards: self.rewards,
+            admin: self.admin,
+            admin_token: self.admin_token,
+            user: self.user,
+            user_token: self.user_token,
+            stake: self.stake,
+        }
+    }
+}
+
+pub fn add_system_account(program_test: &mut ProgramTest, address: &Pubkey, lamports: u64) {
+    program_test.add_account(
+        *address,
+        Account {
+            lamports,
+            owner: system_program::id(),
+            ..Account::default()
+        },
+    );
+}
+
+pub fn add_token_account(
+    program_test: &mut ProgramTest,
+    address: &Pubkey,
+    owner: &Pubkey,
+    amount: u64,
+) {
+    let mut data = vec![0; spl_token::state::Account::LEN];
+    spl_token::state::Account {
+        mint: kittycoin_mint(),
+        owner: *owner,
+        amount,
+        state: spl_token::state::AccountState::Initialized,
+        ..spl_token::state::Account::default()
+    }
+    .pack_into_slice(&mut data);
+    program_test.add_account(
+        *address,
+        Account {
+            lamports: LAMPORTS_PER_SOL,
+            data,
+            owner: spl_token::id(),
+            ..Account::default()
+        },
+    );
+}
+
+/// Program-owned account holding `data` as is, rent exempt.
+pub fn add_raw_account(
+    program_test: &mut ProgramTest,
+    owner: &Pubkey,
+    address: &Pubkey,
+    data: Vec<u8>,
+) {
+    program_test.add_account(
+        *address,
+        Account {
+            lamports: Rent::default().minimum_balance(data.len()),
+            data,
+            owner: *owner,
+            ..Account::default()
+        },
+    );
+}
+
+pub fn add_program_account<T: BorshSerialize>(
+    program_test: &mut ProgramTest,
+    program_id: &Pubkey,
+    address: &Pubkey,
+    state: &T,
+) {
+    program_test.add_account(
+        *address,
+        Account {
+            lamports: LAMPORTS_PER_SOL,
+            data: state.try_to_vec().unwrap(),
+            owner: *program_id,
+            ..Account::default()
+        },
+    );
+}
+
+pub fn add_authority(program_test: &mut ProgramTest, program_id: &Pubkey, authority: &Authority) {
+    add_program_account(
+        program_test,
+        program_id,
+        &get_authority_address(program_id),
+        authority,
+    );
+}
+
+/// Authority account with every role held by `admin`.
+pub fn authority(admin: &Pubkey) -> Authority {
+    Authority {
+        header: Authority::header(),
+        is_initialized: true,
+        pool_admin: *admin,
+        fee_manager: *admin,
+        ballot_creator: *admin,
+        treasury: *admin,
+        guardian: *admin,
+        ..Authority::default()
+    }
+}
+
+pub fn rewards_pool(amount: u64, airdrop_supply: u64, airdrop_fee: u64) -> RewardsPool {
+    RewardsPool {
+        header: RewardsPool::header(),
+        is_initialized: true,
+        amount,
+        airdrop_supply,
+        airdrop_fee,
+        total_coins_staked: 0,
+        total_stakes_count: 0,
+        airdrop_wallet_cap: 0,
+        airdrop_cooldown: 0,
+        airdrop_fee_mint: Pubkey::default(),
+        airdrop_treasury: admin_address(),
+        airdrop_vesting_cliff: 0,
+        airdrop_vesting_duration: 0,
+        vesting_locked: 0,
+        paused: 0,
+    }
+}
+
+pub async fn token_balance(banks_client: &mut BanksClient, address: &Pubkey) -> u64 {
+    let account = banks_client.get_account(*address).await.unwrap().unwrap();
+    spl_token::state::Account::unpack(&account.data)
+        .unwrap()
+        .amount
+}
+
+pub async fn lamports(banks_client: &mut BanksClient, address: &Pubkey) -> u64 {
+    banks_client
+        .get_account(*address)
+        .await
+        .unwrap()
+        .map(|account| account.lamports)
+        .unwrap_or(0)
+}
+
+pub async fn get_state<T: BorshDeserialize>(
+    banks_client: &mut BanksClient,
+    address: &Pubkey,
+) -> Option<T> {
+    banks_client
+        .get_account(*address)
+        .await
+        .unwrap()
+        .map(|account| T::try_from_slice(&account.data).unwrap())
+}
+
+/// What a transaction failing in its only instruction with `err` returns.
+pub fn error(err: InstructionError) -> TransactionError {
+    TransactionError::InstructionError(0, err)
+}
+
+/// An instruction rejected by the program's own checks.
+pub fn rejected() -> TransactionError {
+    error(InstructionError::InvalidInstructionData)
+}
+
+/// Sends `ix` paid for by the context payer and signed by `signers`.
+pub async fn send(
+    context: &mut ProgramTestContext,
+    ix: Instruction,
+    signers: &[&Keypair],
+) -> Result<(), TransactionError> {
+    let mut all_signers = vec![&context.payer];
+    all_signers.extend_from_slice(signers);
+    let tx = Transaction::new_signed_with_payer(
+        &[ix],
+        Some(&context.payer.pubkey()),
+        &all_signers,
+        context.last_blockhash,
+    );
+    context
+        .banks_client
+        .process_transaction(tx)
+        .await
+        .map_err(|err| err.unwrap())
+}
@@ -0,0 +1,292 @@
+//! Syscall stubs layered over program-test's own. Program-test 1.8 drops the return data a
+//! program sets, reads the clock from the bank, whose timestamp barely moves in tests, and cannot
+//! take an account the System Program created under a CPI: it panics copying back the new size
+//! and never commits the new owner. These keep the return data of each program, let a test set
+//! the time its program sees and finish creating the account.
+//!
+//! The native stubs sit behind one lock that `invoke` holds for the whole call while
+//! `set_return_data` needs it exclusively, so a program setting return data under a native CPI
+//! deadlocks. Only the BPF build can be called through a CPI here.
+use borsh::BorshDeserialize;
+use solana_program::{
+    account_info::AccountInfo,
+    clock::Clock,
+    entrypoint::{ProgramResult, SUCCESS},
+    instruction::{Instruction, InstructionError},
+    program_stubs::{self, SyscallStubs},
+    pubkey::Pubkey,
+    system_program,
+};
+use solana_program_test::builtin_process_instruction;
+use solana_sdk::{account::WritableAccount, process_instruction::InvokeContext};
+use std::{
+    collections::{HashMap, HashSet},
+    panic::{self, AssertUnwindSafe},
+    sync::{Mutex, Once, RwLock},
+    thread::{self, ThreadId},
+};
+
+lazy_static::lazy_static! {
+    static ref INNER: RwLock<Option<&'static dyn SyscallStubs>> = RwLock::new(None);
+    static ref RETURN_DATA: Mutex<HashMap<Pubkey, Vec<u8>>> = Mutex::new(HashMap::new());
+    static ref CLOCKS: Mutex<HashMap<Pubkey, i64>> = Mutex::new(HashMap::new());
+    static ref SIGNERS: Mutex<HashMap<Pubkey, HashSet<Pubkey>>> = Mutex::new(HashMap::new());
+    //Owners the System Program assigned under a CPI on each thread, for `process_builtin`.
+    static ref ASSIGNED: Mutex<HashMap<ThreadId, HashMap<Pubkey, Pubkey>>> =
+        Mutex::new(HashMap::new());
+    //The staking program running on each thread.
+    static ref CURRENT_PROGRAM: Mutex<HashMap<ThreadId, Pubkey>> = Mutex::new(HashMap::new());
+    //What `get_return_data` sees on each thread, cleared by every invoke.
+    static ref LAST_RETURN_DATA: Mutex<HashMap<ThreadId, (Pubkey, Vec<u8>)>> =
+        Mutex::new(HashMap::new());
+}
+
+/// The staking program as program-test runs it, then committing the owners of the accounts it
+/// had the System Program assign.
+pub fn process_builtin(
+    program_id: &Pubkey,
+    input: &[u8],
+    invoke_context: &mut dyn InvokeContext,
+) -> Result<(), InstructionError> {
+    builtin_process_instruction(process_instruction, program_id, input, invoke_context)?;
+    let assigned = ASSIGNED
+        .lock()
+        .unwrap()
+        .remove(&thread::current().id())
+        .unwrap_or_default();
+    for keyed_account in invoke_context.get_keyed_accounts()? {
+        if let Some(owner) = assigned.get(keyed_account.unsigned_key()) {
+            keyed_account.account.borrow_mut().set_owner(*owner);
+        }
+    }
+    Ok(())
+}
+
+/// The staking processor, run in place of `staking::processor::process_instruction` so the
+/// stubs know which program is running.
+fn process_instruction(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let thread = thread::current().id();
+    let caller = CURRENT_PROGRAM.lock().unwrap().insert(thread, *program_id);
+    if caller.is_none() {
+        RETURN_DATA.lock().unwrap().remove(program_id);
+    }
+    let signers = SIGNERS.lock().unwrap().get(program_id).cloned();
+    let result = match signers {
+        Some(signers) => {
+            let accounts: Vec<AccountInfo> = accounts
+                .iter()
+                .map(|account| AccountInfo {
+                    is_signer: account.is_signer || signers.contains(account.key),
+                    ..account.clone()
+                })
+                .collect();
+            staking::processor::process_instruction(program_id, &accounts, data)
+        }
+        None => staking::processor::process_instruction(program_id, accounts, data),
+    };
+    match caller {
+        Some(caller) => CURRENT_PROGRAM.lock().unwrap().insert(thread, caller),
+        None => CURRENT_PROGRAM.lock().unwrap().remove(&thread),
+    };
+    result
+}
+
+/// Layers the stubs over program-test's, which it installs when it first starts a bank. Every
+/// test starts through `common::start`, so no program runs while the stubs are swapped.
+pub fn install() {
+    static ONCE: Once = Once::new();
+    ONCE.call_once(|| {
+        let inner = program_stubs::set_syscall_stubs(Box::new(Stubs));
+        *INNER.write().unwrap() = Some(Box::leak(inner));
+    });
+}
+
+/// What the last top-level instruction of `program_id` returned, if it set anything.
+pub fn return_data<T: BorshDeserialize>(program_id: &Pubkey) -> Option<T> {
+    RETURN_DATA
+        .lock()
+        .unwrap()
+        .get(program_id)
+        .map(|data| T::try_from_slice(data).unwrap())
+}
+
+/// Makes `Clock::get` return `unix_timestamp` inside `program_id` from now on.
+pub fn set_unix_timestamp(program_id: &Pubkey, unix_timestamp: i64) {
+    CLOCKS.lock().unwrap().insert(*program_id, unix_timestamp);
+}
+
+/// Makes `program_id` see `address` as a signer, for keys such as the deploy key that no test
+/// holds. The transaction lists it without signing.
+pub fn sign_as(program_id: &Pubkey, address: &Pubkey) {
+    SIGNERS
+        .lock()
+        .unwrap()
+        .entry(*program_id)
+        .or_default()
+        .insert(*address);
+}
+
+/// The account and size of a System Program `CreateAccount` or `Allocate`.
+fn allocation(instruction: &Instruction) -> Option<(Pubkey, usize)> {
+    if instruction.program_id != system_program::id() || instruction.data.len() < 12 {
+        return None;
+    }
+    let space = |at: usize| {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(&instruction.data[at..at + 8]);
+        u64::from_le_bytes(bytes) as usize
+    };
+    match instruction.data[..4] {
+        [0, 0, 0, 0] => Some((instruction.accounts[1].pubkey, space(12))),
+        [8, 0, 0, 0] => Some((instruction.accounts[0].pubkey, space(4))),
+        _ => None,
+    }
+}
+
+/// Invokes a `CreateAccount` or `Allocate` of `space` bytes for `address`. The System Program
+/// has run and the bank took the new size by the time program-test panics copying the data
+/// back, and the resized account comes last, so only its data is left to copy.
+fn invoke_allocation(
+    instruction: &Instruction,
+    account_infos: &[AccountInfo],
+    signers_seeds: &[&[&[u8]]],
+    address: &Pubkey,
+    space: usize,
+) -> ProgramResult {
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        inner().sol_invoke_signed(instruction, account_infos, signers_seeds)
+    }));
+    result.unwrap_or_else(|payload| {
+        let message = payload.downcast_ref::<String>();
+        if !message.is_some_and(|message| message.starts_with("Account data resizing")) {
+            panic::resume_unwind(payload);
+        }
+        let account = account_infos
+            .iter()
+            .find(|account| account.key == address)
+            .unwrap();
+        *account.data.borrow_mut() = Box::leak(vec![0; space].into_boxed_slice());
+        Ok(())
+    })
+}
+
+fn current_program() -> Option<Pubkey> {
+    CURRENT_PROGRAM
+        .lock()
+        .unwrap()
+        .get(&thread::current().id())
+        .copied()
+}
+
+fn inner() -> &'static dyn SyscallStubs {
+    INNER.read().unwrap().expect("stubs installed")
+}
+
+struct Stubs;
+
+impl SyscallStubs for Stubs {
+    fn sol_log(&self, message: &str) {
+        inner().sol_log(message)
+    }
+
+    fn sol_log_compute_units(&self) {
+        inner().sol_log_compute_units()
+    }
+
+    fn sol_invoke_signed(
+        &self,
+        instruction: &Instruction,
+        account_infos: &[AccountInfo],
+        signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        LAST_RETURN_DATA
+            .lock()
+            .unwrap()
+            .remove(&thread::current().id());
+        let owners: Vec<Pubkey> = account_infos.iter().map(|account| *account.owner).collect();
+        let result = match allocation(instruction) {
+            Some((address, space)) => {
+                invoke_allocation(instruction, account_infos, signers_seeds, &address, space)
+            }
+            None => inner().sol_invoke_signed(instruction, account_infos, signers_seeds),
+        };
+        for (account, owner) in account_infos.iter().zip(owners) {
+            if *account.owner != owner {
+                ASSIGNED
+                    .lock()
+                    .unwrap()
+                    .entry(thread::current().id())
+                    .or_default()
+                    .insert(*account.key, *account.owner);
+            }
+        }
+        result
+    }
+
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        let result = inner().sol_get_clock_sysvar(var_addr);
+        let unix_timestamp = current_program()
+            .and_then(|program_id| CLOCKS.lock().unwrap().get(&program_id).copied());
+        if let (SUCCESS, Some(unix_timestamp)) = (result, unix_timestamp) {
+            unsafe { (*(var_addr as *mut Clock)).unix_timestamp = unix_timestamp };
+        }
+        result
+    }
+
+    fn sol_get_epoch_schedule_sysvar(&self, var_addr: *mut u8) -> u64 {
+        inner().sol_get_epoch_schedule_sysvar(var_addr)
+    }
+
+    fn sol_get_fees_sysvar(&self, var_addr: *mut u8) -> u64 {
+        inner().sol_get_fees_sysvar(var_addr)
+    }
+
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        inner().sol_get_rent_sysvar(var_addr)
+    }
+
+    unsafe fn sol_memcpy(&self, dst: *mut u8, src: *const u8, n: usize) {
+        inner().sol_memcpy(dst, src, n)
+    }
+
+    unsafe fn sol_memmove(&self, dst: *mut u8, src: *const u8, n: usize) {
+        inner().sol_memmove(dst, src, n)
+    }
+
+    unsafe fn sol_memcmp(&self, s1: *const u8, s2: *const u8, n: usize, result: *mut i32) {
+        inner().sol_memcmp(s1, s2, n, result)
+    }
+
+    unsafe fn sol_memset(&self, s: *mut u8, c: u8, n: usize) {
+        inner().sol_memset(s, c, n)
+    }
+
+    fn sol_get_return_data(&self) -> Option<(Pubkey, Vec<u8>)> {
+        LAST_RETURN_DATA
+            .lock()
+            .unwrap()
+            .get(&thread::current().id())
+            .cloned()
+    }
+
+    fn sol_set_return_data(&mut self, data: &[u8]) {
+        if let Some(program_id) = current_program() {
+            RETURN_DATA
+                .lock()
+                .unwrap()
+                .insert(program_id, data.to_vec());
+            LAST_RETURN_DATA
+                .lock()
+                .unwrap()
+                .insert(thread::current().id(), (program_id, data.to_vec()));
+        }
+    }
+
+    fn sol_log_data(&self, fields: &[&[u8]]) {
+        inner().sol_log_data(fields)
+    }
+}
@@ -0,0 +1,234 @@
+mod common;
+
+use common::*;
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    instruction::InstructionError,
+    pubkey::Pubkey,
+};
+use solana_program_test::{processor, tokio};
+use solana_sdk::signature::{Keypair, Signer};
+use staking::{cpi, instruction, processor::BASE_COIN, state::Stake};
+
+const STAKE: u64 = 10 * BASE_COIN;
+
+/// Stands in for an integrating program: its PDA owns the stakes its users fund.
+///[0] staking program, then the `create_stake` or `end_stake` accounts with the vault PDA
+fn process_vault(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let staking_program = next_account_info(accounts_iter)?;
+
+    let (_vault, bump_seed) = Pubkey::find_program_address(&[b"vault"], program_id);
+    let signer_seeds: &[&[u8]] = &[b"vault", &[bump_seed]];
+
+    if data[0] == 0 {
+        let user = next_account_info(accounts_iter)?;
+        let user_token = next_account_info(accounts_iter)?;
+        let vault = next_account_info(accounts_iter)?;
+        let stake = next_account_info(accounts_iter)?;
+        let pda_token = next_account_info(accounts_iter)?;
+        let token_program = next_account_info(accounts_iter)?;
+        let rewards = next_account_info(accounts_iter)?;
+        cpi::create_stake(
+            staking_program.clone(),
+            cpi::CreateStakeAccounts {
+                owner: user.clone(),
+                owner_token: user_token.clone(),
+                stake: stake.clone(),
+                pda_token: pda_token.clone(),
+                token_program: token_program.clone(),
+                rewards: rewards.clone(),
+                stake_owner: Some(vault.clone()),
+            },
+            STAKE,
+            &[signer_seeds],
+        )
+    } else {
+        let vault = next_account_info(accounts_iter)?;
+        let vault_token = next_account_info(accounts_iter)?;
+        let stake = next_account_info(accounts_iter)?;
+        let pda = next_account_info(accounts_iter)?;
+        let pda_token = next_account_info(accounts_iter)?;
+        let token_program = next_account_info(accounts_iter)?;
+        let rewards = next_account_info(accounts_iter)?;
+        cpi::end_stake(
+            staking_program.clone(),
+            cpi::EndStakeAccounts {
+                owner: vault.clone(),
+                owner_token: vault_token.clone(),
+                stake: stake.clone(),
+                pda: pda.clone(),
+                pda_token: pda_token.clone(),
+                token_program: token_program.clone(),
+                rewards: rewards.clone(),
+            },
+            &[signer_seeds],
+        )
+    }
+}
+
+/// The integrating program and the PDA that holds its users' stakes.
+struct Vault {
+    program_id: Pubkey,
+    address: Pubkey,
+    token: Pubkey,
+}
+
+/// An empty pool, a zeroed stake account, a user holding `STAKE` and the vault program.
+async fn setup() -> (Setup, Vault) {
+    setup_with(SetupBuilder::new(0)).await
+}
+
+async fn setup_with(builder: SetupBuilder) -> (Setup, Vault) {
+    let program_id = Pubkey::new_unique();
+    let (address, _bump_seed) = Pubkey::find_program_address(&[b"vault"], &program_id);
+    let vault = Vault {
+        program_id,
+        address,
+        token: Pubkey::new_unique(),
+    };
+
+    let mut builder = SetupBuilder {
+        user_coins: STAKE,
+        ..builder
+    };
+    builder
+        .program_test
+        .add_program("vault", vault.program_id, processor!(process_vault));
+    add_token_account(&mut builder.program_test, &vault.token, &vault.address, 0);
+    (builder.start().await, vault)
+}
+
+//A program setting return data under a native CPI deadlocks on the syscall stubs' lock, see
+//`common::stubs`, so the vault calls the BPF build here.
+#[cfg(feature = "test-bpf")]
+mod bpf {
+    use super::*;
+    use solana_program::{
+        instruction::{AccountMeta, Instruction},
+        rent::Rent,
+    };
+    use staking::{
+        get_pda_address,
+        state::{AccountState, RewardsPool},
+    };
+
+    #[tokio::test]
+    async fn test_program_pda_owns_stake_funded_by_user() {
+        let program_id = Pubkey::new_unique();
+        let (mut setup, vault) = setup_with(SetupBuilder {
+            program_test: bpf_program_test(&program_id, &Pubkey::new_unique(), 0),
+            ..SetupBuilder::with_program_id(program_id, 0)
+        })
+        .await;
+
+        let ix = Instruction {
+            program_id: vault.program_id,
+            accounts: vec![
+                AccountMeta::new_readonly(program_id, false),
+                AccountMeta::new_readonly(setup.user.pubkey(), true),
+                AccountMeta::new(setup.user_token, false),
+                AccountMeta::new(vault.address, false),
+                AccountMeta::new(setup.stake, false),
+                AccountMeta::new(pda_token_address(&program_id), false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+                AccountMeta::new(setup.rewards, false),
+            ],
+            data: vec![0],
+        };
+        send(&mut setup.context, ix, &[&setup.user]).await.unwrap();
+
+        let banks_client = &mut setup.context.banks_client;
+        let stake = get_state::<Stake>(banks_client, &setup.stake)
+            .await
+            .unwrap();
+        assert_eq!(stake.owner, vault.address);
+        assert_eq!(stake.amount, STAKE);
+        assert_eq!(token_balance(banks_client, &setup.user_token).await, 0);
+        let pool = get_state::<RewardsPool>(banks_client, &setup.rewards)
+            .await
+            .unwrap();
+        assert_eq!(pool.total_coins_staked, STAKE);
+
+        //Only the vault program can end the stake, the user gets nothing back directly.
+        let ix = instruction::end_stake(
+            &program_id,
+            &setup.user.pubkey(),
+            &setup.user_token,
+            &setup.stake,
+            &setup.rewards,
+        );
+        assert_eq!(
+            send(&mut setup.context, ix, &[&setup.user])
+                .await
+                .unwrap_err(),
+            error(InstructionError::IllegalOwner)
+        );
+
+        let ix = Instruction {
+            program_id: vault.program_id,
+            accounts: vec![
+                AccountMeta::new_readonly(program_id, false),
+                AccountMeta::new(vault.address, false),
+                AccountMeta::new(vault.token, false),
+                AccountMeta::new(setup.stake, false),
+                AccountMeta::new_readonly(get_pda_address(&program_id), false),
+                AccountMeta::new(pda_token_address(&program_id), false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+                AccountMeta::new(setup.rewards, false),
+            ],
+            data: vec![1],
+        };
+        send(&mut setup.context, ix, &[]).await.unwrap();
+
+        let banks_client = &mut setup.context.banks_client;
+        assert_eq!(token_balance(banks_client, &vault.token).await, STAKE);
+        //The stake account's rent goes to its owner.
+        assert_eq!(
+            lamports(banks_client, &vault.address).await,
+            Rent::default().minimum_balance(Stake::LEN)
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_create_stake_for_requires_owner_signature() {
+    let (mut setup, _vault) = setup().await;
+    let owner = Keypair::new();
+
+    let mut ix = instruction::create_stake_for(
+        &setup.program_id,
+        &setup.user.pubkey(),
+        &setup.user_token,
+        &owner.pubkey(),
+        &setup.stake,
+        &setup.rewards,
+        STAKE,
+    );
+    ix.accounts[6].is_signer = false;
+    assert_eq!(
+        send(&mut setup.context, ix, &[&setup.user])
+            .await
+            .unwrap_err(),
+        error(InstructionError::MissingRequiredSignature)
+    );
+
+    let ix = instruction::create_stake_for(
+        &setup.program_id,
+        &setup.user.pubkey(),
+        &setup.user_token,
+        &owner.pubkey(),
+        &setup.stake,
+        &setup.rewards,
+        STAKE,
+    );
+    send(&mut setup.context, ix, &[&setup.user, &owner])
+        .await
+        .unwrap();
+
+    let stake = get_state::<Stake>(&mut setup.context.banks_client, &setup.stake)
+        .await
+        .unwrap();
+    assert_eq!(stake.owner, owner.pubkey());
+}
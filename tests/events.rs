@@ -0,0 +1,109 @@
+use borsh::BorshSerialize;
+use solana_program::pubkey::Pubkey;
+use staking::{
+    event::{Event, EVENT_TAG},
+    state::PoolBucket,
+};
+
+fn log_line(fields: &[&[u8]]) -> String {
+    let fields: Vec<String> = fields.iter().map(base64::encode).collect();
+    format!("Program data: {}", fields.join(" "))
+}
+
+fn event_line(event: &Event) -> String {
+    log_line(&[EVENT_TAG, &event.try_to_vec().unwrap()])
+}
+
+#[test]
+fn test_log_line_round_trip() {
+    let event = Event::StakeEnded {
+        owner: Pubkey::new_unique(),
+        stake: Pubkey::new_unique(),
+        principal: 100,
+        reward: 7,
+    };
+    assert_eq!(Event::from_log(&event_line(&event)), Some(event));
+
+    let event = Event::Rebalanced {
+        from: PoolBucket::Airdrop,
+        to: PoolBucket::Rewards,
+        amount: 5,
+    };
+    assert_eq!(Event::from_log(&event_line(&event)), Some(event));
+}
+
+#[test]
+fn test_schema_is_stable() {
+    //Indexers key on the variant byte, appending variants must not renumber these.
+    assert_eq!(
+        Event::StakeCreated {
+            owner: Pubkey::default(),
+            stake: Pubkey::default(),
+            amount: 0,
+            time_started: 0,
+        }
+        .try_to_vec()
+        .unwrap()[0],
+        0
+    );
+    assert_eq!(
+        Event::FeeChanged { fee: 1 }.try_to_vec().unwrap(),
+        [7, 1, 0, 0, 0, 0, 0, 0, 0]
+    );
+    assert_eq!(Event::AdminRenounced.try_to_vec().unwrap(), [18]);
+    assert_eq!(
+        Event::AccountMigrated {
+            legacy: Pubkey::default(),
+            new: Pubkey::default(),
+        }
+        .try_to_vec()
+        .unwrap()[0],
+        23
+    );
+    assert_eq!(
+        Event::PoolAccountingSkipped {
+            rewards: Pubkey::default(),
+            stake: Pubkey::default(),
+        }
+        .try_to_vec()
+        .unwrap()[0],
+        24
+    );
+}
+
+#[test]
+fn test_ignores_other_log_lines() {
+    let data = Event::FeeChanged { fee: 1 }.try_to_vec().unwrap();
+    assert_eq!(Event::from_log("Program log: Instruction: Vote"), None);
+    assert_eq!(Event::from_log(&log_line(&[&data])), None);
+    assert_eq!(Event::from_log(&log_line(&[b"other", &data])), None);
+    assert_eq!(Event::from_log(&log_line(&[EVENT_TAG, &[255]])), None);
+    assert_eq!(Event::from_log("Program data: not-base64!"), None);
+}
+
+#[test]
+fn test_from_logs_only_reads_our_invocations() {
+    let program_id = Pubkey::new_unique();
+    let other_program = Pubkey::new_unique();
+    let ours = Event::PauseChanged { paused: 1 };
+    let spoofed = Event::PauseChanged { paused: 0 };
+    let inner = Event::FeeChanged { fee: 3 };
+
+    let logs = vec![
+        format!("Program {} invoke [1]", other_program),
+        event_line(&spoofed),
+        format!("Program {} invoke [2]", program_id),
+        event_line(&inner),
+        format!(
+            "Program {} consumed 100 of 200000 compute units",
+            program_id
+        ),
+        format!("Program {} success", program_id),
+        format!("Program {} success", other_program),
+        format!("Program {} invoke [1]", program_id),
+        "Program log: Instruction: SetPause".to_string(),
+        event_line(&ours),
+        format!("Program {} success", program_id),
+    ];
+    assert_eq!(Event::from_logs(&program_id, &logs), vec![inner, ours]);
+}
@@ -0,0 +1,158 @@
+mod common;
+
+use borsh::BorshSerialize;
+use common::*;
+use solana_program::pubkey::Pubkey;
+use solana_program_test::tokio;
+use solana_sdk::{account::Account, signature::Signer};
+use staking::{
+    error::StakingError,
+    indexer::{self, ProgramAccount, Tvl},
+    instruction,
+    processor::{BASE_COIN, COINS_FOR_1_INTEREST},
+    state::{legacy, AccountState, RewardsPool, Stake, VestingContract},
+};
+
+const POOL_AMOUNT: u64 = 1_000 * BASE_COIN;
+const STAKE_AMOUNT: u64 = 100 * BASE_COIN;
+const VESTING_AMOUNT: u64 = 50 * BASE_COIN;
+
+/// Accounts written by the program: a pool, a stake created through `CreateStake` and a
+/// half released vesting contract.
+async fn fixtures() -> Vec<(Pubkey, Account)> {
+    let contract = Pubkey::new_unique();
+    let mut builder = SetupBuilder {
+        pool: Some(RewardsPool {
+            airdrop_supply: 20 * BASE_COIN,
+            vesting_locked: 5 * BASE_COIN,
+            ..rewards_pool(POOL_AMOUNT, 0, 0)
+        }),
+        user_coins: STAKE_AMOUNT,
+        ..SetupBuilder::new(POOL_AMOUNT)
+    };
+    add_program_account(
+        &mut builder.program_test,
+        &builder.program_id,
+        &contract,
+        &VestingContract {
+            header: VestingContract::header(),
+            is_initialized: true,
+            total: VESTING_AMOUNT,
+            released: VESTING_AMOUNT / 2,
+            ..VestingContract::default()
+        },
+    );
+
+    let mut setup = builder.start().await;
+    let ix = instruction::create_stake(
+        &setup.program_id,
+        &setup.user.pubkey(),
+        &setup.user_token,
+        &setup.stake,
+        &setup.rewards,
+        STAKE_AMOUNT,
+    );
+    send(&mut setup.context, ix, &[&setup.user]).await.unwrap();
+
+    let mut accounts = Vec::new();
+    for address in [setup.rewards, setup.stake, contract] {
+        let account = setup
+            .context
+            .banks_client
+            .get_account(address)
+            .await
+            .unwrap()
+            .unwrap();
+        accounts.push((address, account));
+    }
+    accounts
+}
+
+#[tokio::test]
+async fn test_decodes_program_accounts() {
+    let fixtures = fixtures().await;
+
+    let accounts: Vec<ProgramAccount> = fixtures
+        .iter()
+        .map(|(_, account)| ProgramAccount::decode(&account.data).unwrap())
+        .collect();
+    match &accounts[..] {
+        [ProgramAccount::RewardsPool(pool), ProgramAccount::Stake(stake), ProgramAccount::VestingContract(_)] =>
+        {
+            assert_eq!(pool.total_coins_staked, STAKE_AMOUNT);
+            assert_eq!(pool.total_stakes_count, 1);
+            assert_eq!(stake.amount, STAKE_AMOUNT);
+        }
+        other => panic!("unexpected accounts {:?}", other),
+    }
+
+    assert_eq!(
+        indexer::tvl(&accounts),
+        Ok(Tvl {
+            staked: STAKE_AMOUNT,
+            stakes_count: 1,
+            rewards: POOL_AMOUNT,
+            airdrop: 20 * BASE_COIN,
+            vesting_locked: 5 * BASE_COIN,
+            vesting_contracts: VESTING_AMOUNT / 2,
+        })
+    );
+    assert_eq!(
+        indexer::tvl(&accounts).unwrap().total(),
+        Ok(STAKE_AMOUNT + POOL_AMOUNT + 25 * BASE_COIN + VESTING_AMOUNT / 2)
+    );
+    let overflowing = Tvl {
+        staked: u64::MAX,
+        rewards: 1,
+        ..Tvl::default()
+    };
+    assert_eq!(overflowing.total(), Err(StakingError::MathOverflow.into()));
+
+    let stake: Stake = indexer::decode(&fixtures[1].1.data).unwrap();
+    assert_eq!(stake.amount, STAKE_AMOUNT);
+    assert!(indexer::decode::<RewardsPool>(&fixtures[1].1.data).is_err());
+}
+
+#[test]
+fn test_rejects_unknown_and_legacy_data() {
+    assert!(ProgramAccount::decode(&[]).is_err());
+    assert!(ProgramAccount::decode(&[7; 64]).is_err());
+
+    let legacy_stake = legacy::Stake {
+        is_initialized: true,
+        owner: Pubkey::new_unique(),
+        amount: STAKE_AMOUNT,
+        time_started: 0,
+    };
+    assert!(ProgramAccount::decode(&legacy_stake.try_to_vec().unwrap()).is_err());
+}
+
+#[test]
+fn test_pending_reward_matches_end_stake() {
+    let stake = Stake {
+        header: Stake::header(),
+        is_initialized: true,
+        owner: Pubkey::new_unique(),
+        amount: 10 * COINS_FOR_1_INTEREST,
+        time_started: 1_000,
+    };
+    let pool = rewards_pool(POOL_AMOUNT, 0, 0);
+
+    assert_eq!(indexer::pending_reward(&stake, &pool, 1_000), 0);
+    assert_eq!(indexer::pending_reward(&stake, &pool, 1_005), 0);
+    assert_eq!(indexer::pending_reward(&stake, &pool, 1_059), 9);
+    assert_eq!(indexer::pending_reward(&stake, &pool, 1_000 + 60 * 30), 300);
+    assert_eq!(indexer::pending_reward(&stake, &pool, 999), 0);
+
+    let paused = RewardsPool {
+        paused: RewardsPool::PAUSE_REWARDS,
+        ..rewards_pool(POOL_AMOUNT, 0, 0)
+    };
+    assert_eq!(indexer::pending_reward(&stake, &paused, 1_000 + 60 * 30), 0);
+
+    let underfunded = rewards_pool(299, 0, 0);
+    assert_eq!(
+        indexer::pending_reward(&stake, &underfunded, 1_000 + 60 * 30),
+        0
+    );
+}
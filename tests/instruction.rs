@@ -0,0 +1,127 @@
+use solana_program::{program_error::ProgramError, pubkey::Pubkey};
+use staking::{
+    instruction::Instruction,
+    state::{AuthorityType, PoolBucket},
+};
+
+/// One of each variant, in tag order.
+fn every_instruction() -> Vec<Instruction> {
+    let key = Pubkey::new_unique();
+    vec![
+        Instruction::CreateStake { amount: 1 },
+        Instruction::EndStake,
+        Instruction::AddToRewardsPool {
+            amount: 2,
+            airdrop: true,
+        },
+        Instruction::Vote { selection: 4 },
+        Instruction::Airdrop { amount: 3 },
+        Instruction::ChangeAirdropFee { fee: 4 },
+        Instruction::SetAirdropLimits {
+            wallet_cap: 5,
+            cooldown: -6,
+        },
+        Instruction::SetAirdropTreasury {
+            fee_mint: key,
+            treasury: Pubkey::new_unique(),
+        },
+        Instruction::SetAirdropVesting {
+            cliff: 7,
+            duration: 8,
+        },
+        Instruction::ReleaseVested,
+        Instruction::CreateVesting {
+            id: 9,
+            start: -10,
+            cliff: 11,
+            end: 12,
+            amount: 13,
+            revocable: true,
+        },
+        Instruction::Withdraw,
+        Instruction::RevokeVesting,
+        Instruction::InitializeAuthority,
+        Instruction::SetAuthority {
+            authority_type: AuthorityType::Guardian,
+            new_authority: key,
+        },
+        Instruction::ProposeAdmin { new_admin: key },
+        Instruction::AcceptAdmin,
+        Instruction::RenounceAdmin,
+        Instruction::InitializeMultisig { m: 2 },
+        Instruction::SetPause { paused: 3 },
+        Instruction::EmergencyWithdraw,
+        Instruction::WithdrawFromPool {
+            bucket: PoolBucket::Airdrop,
+            amount: 14,
+        },
+        Instruction::Rebalance {
+            from: PoolBucket::Airdrop,
+            to: PoolBucket::Rewards,
+            amount: 15,
+        },
+        Instruction::MigrateAccount,
+        Instruction::SimulateReward {
+            amount: 16,
+            duration: 17,
+        },
+    ]
+}
+
+#[test]
+fn test_every_variant_round_trips() {
+    for (tag, instruction) in every_instruction().iter().enumerate() {
+        let data = instruction.pack();
+        assert_eq!(data[0] as usize, tag);
+        let unpacked = Instruction::unpack(&data).unwrap();
+        assert_eq!(unpacked.pack(), data);
+    }
+}
+
+#[test]
+fn test_rejects_truncated_data() {
+    assert_eq!(
+        Instruction::unpack(&[]).err(),
+        Some(ProgramError::InvalidInstructionData)
+    );
+    for instruction in every_instruction() {
+        let data = instruction.pack();
+        for len in 1..data.len() {
+            assert_eq!(
+                Instruction::unpack(&data[..len]).err(),
+                Some(ProgramError::InvalidInstructionData),
+                "tag {} cut to {} bytes",
+                data[0],
+                len
+            );
+        }
+    }
+}
+
+#[test]
+fn test_rejects_unknown_tags_and_enum_values() {
+    let count = every_instruction().len() as u8;
+    for tag in count..=u8::MAX {
+        assert_eq!(
+            Instruction::unpack(&[tag; 64]).err(),
+            Some(ProgramError::InvalidInstructionData)
+        );
+    }
+
+    let mut data = Instruction::SetAuthority {
+        authority_type: AuthorityType::PoolAdmin,
+        new_authority: Pubkey::new_unique(),
+    }
+    .pack();
+    data[1] = 5;
+    assert!(Instruction::unpack(&data).is_err());
+
+    let mut data = Instruction::Rebalance {
+        from: PoolBucket::Rewards,
+        to: PoolBucket::Airdrop,
+        amount: 1,
+    }
+    .pack();
+    data[2] = 2;
+    assert!(Instruction::unpack(&data).is_err());
+}
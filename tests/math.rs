@@ -0,0 +1,120 @@
+mod common;
+
+use borsh::BorshSerialize;
+use common::*;
+use solana_program::{instruction::InstructionError, program_error::ProgramError, pubkey::Pubkey};
+use solana_program_test::tokio;
+use solana_sdk::signature::Signer;
+use staking::{
+    error::StakingError,
+    instruction,
+    math::{self, CheckedMath},
+    processor::{BASE_COIN, COINS_FOR_1_INTEREST},
+    reward,
+    state::{self, AccountState, RewardsPool, Stake},
+    zero_copy::{self, RewardsPoolData},
+};
+
+fn overflow() -> ProgramError {
+    StakingError::MathOverflow.into()
+}
+
+#[test]
+fn test_checked_math_reports_overflow() {
+    assert_eq!(overflow(), ProgramError::Custom(0));
+    assert_eq!(u64::MAX.try_add(0), Ok(u64::MAX));
+    assert_eq!(u64::MAX.try_add(1), Err(overflow()));
+    assert_eq!(0u64.try_sub(1), Err(overflow()));
+    assert_eq!(u32::MAX.try_mul(2), Err(overflow()));
+    assert_eq!(1u64.try_div(0), Err(overflow()));
+    assert_eq!(i64::MAX.try_add(1), Err(overflow()));
+    assert_eq!(i64::MIN.try_sub(1), Err(overflow()));
+    assert_eq!(math::to_u64(u64::MAX as u128 + 1), Err(overflow()));
+}
+
+#[test]
+fn test_mul_div_multiplies_first() {
+    assert_eq!(math::mul_div(u64::MAX, 3, 3), Ok(u64::MAX));
+    assert_eq!(math::mul_div(7, 10, 4), Ok(17));
+    assert_eq!(math::mul_div(u64::MAX, 2, 1), Err(overflow()));
+    assert_eq!(math::mul_div(1, 1, 0), Err(overflow()));
+    assert_eq!(math::ceil_div(6, 3), Ok(2));
+    assert_eq!(math::ceil_div(7, 3), Ok(3));
+    assert_eq!(math::ceil_div(0, 3), Ok(0));
+    assert_eq!(math::ceil_div(1, 0), Err(overflow()));
+    assert_eq!(math::ceil_div(u128::MAX, 2), Err(overflow()));
+}
+
+#[test]
+fn test_vested_amount_rejects_unbounded_schedules() {
+    assert_eq!(state::vested_amount(u64::MAX, 0, 0, 4, 1), Ok(u64::MAX / 4));
+    assert_eq!(
+        state::vested_amount(100, i64::MIN, 0, i64::MAX, 0),
+        Err(overflow())
+    );
+    //A cliff before the start must not turn the elapsed time negative.
+    assert_eq!(state::vested_amount(100, 10, 0, 20, 5), Err(overflow()));
+    assert_eq!(state::vested_amount(100, i64::MIN, i64::MIN, 0, 0), Ok(100));
+}
+
+#[test]
+fn test_reward_math_keeps_precision_and_never_wraps() {
+    //Half a COINS_FOR_1_INTEREST still earns over two minutes.
+    assert_eq!(reward::accrued_reward(COINS_FOR_1_INTEREST / 2, 0, 120), 1);
+    assert_eq!(
+        reward::accrued_reward(u64::MAX, i64::MIN, i64::MAX),
+        u64::MAX
+    );
+
+    let stake = Stake {
+        header: Stake::header(),
+        is_initialized: true,
+        owner: Pubkey::new_unique(),
+        amount: BASE_COIN,
+        time_started: 0,
+    };
+    let pool = rewards_pool(0, 0, 0);
+    //No stake is counted in the pool, so there is nothing to take it off.
+    let mut pool = *zero_copy::view::<RewardsPoolData>(&pool.try_to_vec().unwrap()).unwrap();
+    assert_eq!(reward::settle_stake(&stake, &mut pool, 0), Err(overflow()));
+}
+
+#[tokio::test]
+async fn test_create_stake_overflowing_pool_counters_fails() {
+    let mut setup = SetupBuilder {
+        pool: Some(RewardsPool {
+            total_coins_staked: u64::MAX - BASE_COIN + 1,
+            total_stakes_count: 1,
+            ..rewards_pool(0, 0, 0)
+        }),
+        user_coins: BASE_COIN,
+        ..SetupBuilder::new(0)
+    }
+    .start()
+    .await;
+
+    let ix = instruction::create_stake(
+        &setup.program_id,
+        &setup.user.pubkey(),
+        &setup.user_token,
+        &setup.stake,
+        &setup.rewards,
+        BASE_COIN,
+    );
+    assert_eq!(
+        send(&mut setup.context, ix, &[&setup.user])
+            .await
+            .unwrap_err(),
+        error(InstructionError::Custom(0))
+    );
+
+    let banks_client = &mut setup.context.banks_client;
+    assert_eq!(
+        token_balance(banks_client, &setup.user_token).await,
+        BASE_COIN
+    );
+    let pool = get_state::<RewardsPool>(banks_client, &setup.rewards)
+        .await
+        .unwrap();
+    assert_eq!(pool.total_coins_staked, u64::MAX - BASE_COIN + 1);
+}
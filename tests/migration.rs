@@ -0,0 +1,212 @@
+mod common;
+
+use borsh::BorshSerialize;
+use common::*;
+use solana_program::{instruction::InstructionError, pubkey::Pubkey, rent::Rent};
+use solana_program_test::tokio;
+use solana_sdk::signature::Signer;
+use staking::{
+    instruction,
+    processor::BASE_COIN,
+    state::{
+        legacy, AccountState, AirdropReceipt, Authority, Ballot, Multisig, RewardsPool, Stake,
+        VestingContract, VestingSchedule,
+    },
+};
+
+//The account being migrated and the zeroed one it moves into.
+const LEGACY: Pubkey = Pubkey::new_from_array([4; 32]);
+const NEW: Pubkey = Pubkey::new_from_array([5; 32]);
+
+/// Preloads `legacy_data` and a zeroed `new_len` account to migrate it into.
+async fn setup(legacy_data: impl FnOnce(&Pubkey) -> Vec<u8>, new_len: usize) -> Setup {
+    let mut builder = SetupBuilder::new(0);
+    let data = legacy_data(&builder.user.pubkey());
+    add_raw_account(
+        &mut builder.program_test,
+        &builder.program_id,
+        &LEGACY,
+        data,
+    );
+    add_raw_account(
+        &mut builder.program_test,
+        &builder.program_id,
+        &NEW,
+        vec![0; new_len],
+    );
+    builder.start().await
+}
+
+fn legacy_stake(owner: &Pubkey) -> Vec<u8> {
+    legacy::Stake {
+        is_initialized: true,
+        owner: *owner,
+        amount: 10 * BASE_COIN,
+        time_started: 1_600_000_000,
+    }
+    .try_to_vec()
+    .unwrap()
+}
+
+fn legacy_pool(_owner: &Pubkey) -> Vec<u8> {
+    legacy::RewardsPool {
+        is_initialized: true,
+        amount: 500 * BASE_COIN,
+        airdrop_supply: 200 * BASE_COIN,
+        airdrop_fee: 100_000,
+        total_coins_staked: 10 * BASE_COIN,
+        total_stakes_count: 1,
+    }
+    .try_to_vec()
+    .unwrap()
+}
+
+fn current_stake(owner: &Pubkey) -> Vec<u8> {
+    Stake {
+        header: Stake::header(),
+        is_initialized: true,
+        owner: *owner,
+        amount: BASE_COIN,
+        time_started: 0,
+    }
+    .try_to_vec()
+    .unwrap()
+}
+
+#[test]
+fn test_len_matches_serialized_size() {
+    assert_eq!(Stake::default().try_to_vec().unwrap().len(), Stake::LEN);
+    assert_eq!(
+        RewardsPool::default().try_to_vec().unwrap().len(),
+        RewardsPool::LEN
+    );
+    assert_eq!(Ballot::default().try_to_vec().unwrap().len(), Ballot::LEN);
+    assert_eq!(
+        AirdropReceipt::default().try_to_vec().unwrap().len(),
+        AirdropReceipt::LEN
+    );
+    assert_eq!(
+        VestingSchedule::default().try_to_vec().unwrap().len(),
+        VestingSchedule::LEN
+    );
+    assert_eq!(
+        VestingContract::default().try_to_vec().unwrap().len(),
+        VestingContract::LEN
+    );
+    assert_eq!(
+        Authority::default().try_to_vec().unwrap().len(),
+        Authority::LEN
+    );
+    assert_eq!(
+        Multisig::default().try_to_vec().unwrap().len(),
+        Multisig::LEN
+    );
+}
+
+#[tokio::test]
+async fn test_migrate_stake() {
+    let mut setup = setup(legacy_stake, Stake::LEN).await;
+    let user_lamports = lamports(&mut setup.context.banks_client, &setup.user.pubkey()).await;
+
+    let ix =
+        instruction::migrate_account(&setup.program_id, &setup.user.pubkey(), &[], &LEGACY, &NEW);
+    send(&mut setup.context, ix, &[&setup.user]).await.unwrap();
+
+    let stake: Stake = get_state(&mut setup.context.banks_client, &NEW)
+        .await
+        .unwrap();
+    assert_eq!(stake.header, Stake::header());
+    assert!(stake.is_initialized);
+    assert_eq!(stake.owner, setup.user.pubkey());
+    assert_eq!(stake.amount, 10 * BASE_COIN);
+    assert_eq!(stake.time_started, 1_600_000_000);
+
+    //The legacy account's rent goes back to the owner and the account is closed.
+    assert_eq!(
+        lamports(&mut setup.context.banks_client, &setup.user.pubkey()).await,
+        user_lamports + Rent::default().minimum_balance(legacy::Stake::LEN)
+    );
+    assert!(setup
+        .context
+        .banks_client
+        .get_account(LEGACY)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[tokio::test]
+async fn test_migrate_stake_requires_owner() {
+    let mut setup = setup(legacy_stake, Stake::LEN).await;
+
+    let ix =
+        instruction::migrate_account(&setup.program_id, &setup.admin.pubkey(), &[], &LEGACY, &NEW);
+    assert_eq!(
+        send(&mut setup.context, ix, &[&setup.admin])
+            .await
+            .unwrap_err(),
+        error(InstructionError::IllegalOwner)
+    );
+}
+
+#[tokio::test]
+async fn test_migrate_pool() {
+    let mut setup = setup(legacy_pool, RewardsPool::LEN).await;
+
+    let ix =
+        instruction::migrate_account(&setup.program_id, &setup.admin.pubkey(), &[], &LEGACY, &NEW);
+    send(&mut setup.context, ix, &[&setup.admin]).await.unwrap();
+
+    let pool: RewardsPool = get_state(&mut setup.context.banks_client, &NEW)
+        .await
+        .unwrap();
+    assert_eq!(pool.header, RewardsPool::header());
+    assert_eq!(pool.amount, 500 * BASE_COIN);
+    assert_eq!(pool.airdrop_supply, 200 * BASE_COIN);
+    assert_eq!(pool.airdrop_fee, 100_000);
+    assert_eq!(pool.total_coins_staked, 10 * BASE_COIN);
+    assert_eq!(pool.total_stakes_count, 1);
+    assert_eq!(pool.airdrop_treasury, setup.admin.pubkey());
+    assert_eq!(pool.paused, 0);
+}
+
+#[tokio::test]
+async fn test_migrate_pool_requires_pool_admin() {
+    let mut setup = setup(legacy_pool, RewardsPool::LEN).await;
+
+    let ix =
+        instruction::migrate_account(&setup.program_id, &setup.user.pubkey(), &[], &LEGACY, &NEW);
+    assert_eq!(
+        send(&mut setup.context, ix, &[&setup.user])
+            .await
+            .unwrap_err(),
+        rejected()
+    );
+}
+
+#[tokio::test]
+async fn test_migrate_rejects_wrong_size_target() {
+    let mut setup = setup(legacy_stake, RewardsPool::LEN).await;
+
+    let ix =
+        instruction::migrate_account(&setup.program_id, &setup.user.pubkey(), &[], &LEGACY, &NEW);
+    assert_eq!(
+        send(&mut setup.context, ix, &[&setup.user])
+            .await
+            .unwrap_err(),
+        error(InstructionError::InvalidAccountData)
+    );
+}
+
+#[tokio::test]
+async fn test_migrate_rejects_current_layout() {
+    let mut setup = setup(current_stake, Stake::LEN).await;
+    let ix =
+        instruction::migrate_account(&setup.program_id, &setup.user.pubkey(), &[], &LEGACY, &NEW);
+    assert_eq!(
+        send(&mut setup.context, ix, &[&setup.user])
+            .await
+            .unwrap_err(),
+        error(InstructionError::InvalidAccountData)
+    );
+}
@@ -0,0 +1,368 @@
+mod common;
+
+use common::*;
+use solana_program::{instruction::InstructionError, pubkey::Pubkey};
+use solana_program_test::tokio;
+use solana_sdk::{
+    native_token::LAMPORTS_PER_SOL,
+    signature::{Keypair, Signer},
+    system_instruction,
+    transaction::TransactionError,
+};
+use staking::{
+    instruction,
+    processor::BASE_COIN,
+    return_data::StakeEnded,
+    reward,
+    state::{AccountHeader, AccountState, Authority, RewardsPool, Stake},
+};
+const STAKE_AMOUNT: u64 = 100 * BASE_COIN;
+const POOL_AMOUNT: u64 = 1_000 * BASE_COIN;
+const STARTED: i64 = 1_700_000_000;
+const AN_HOUR: i64 = 3600;
+
+/// A pool with `paused` flags and one stake, an hour old by the program's clock.
+async fn setup(paused: u8) -> Setup {
+    setup_with_pool(pool(paused), &Pubkey::new_unique()).await
+}
+
+fn pool(paused: u8) -> RewardsPool {
+    RewardsPool {
+        total_coins_staked: STAKE_AMOUNT,
+        total_stakes_count: 1,
+        paused,
+        ..rewards_pool(POOL_AMOUNT, 0, 0)
+    }
+}
+
+/// The stake of `setup` next to `pool`, which need not count it, with `guardian` as guardian.
+async fn setup_with_pool(pool: RewardsPool, guardian: &Pubkey) -> Setup {
+    let builder = SetupBuilder::new(POOL_AMOUNT + STAKE_AMOUNT);
+    let stake = Stake {
+        header: Stake::header(),
+        is_initialized: true,
+        owner: builder.user.pubkey(),
+        amount: STAKE_AMOUNT,
+        time_started: STARTED,
+    };
+    let setup = SetupBuilder {
+        pool: Some(pool),
+        authority: Authority {
+            guardian: *guardian,
+            ..builder.authority
+        },
+        user_coins: STAKE_AMOUNT,
+        stake_state: Some(stake),
+        ..builder
+    }
+    .start()
+    .await;
+    stubs::set_unix_timestamp(&setup.program_id, STARTED + AN_HOUR);
+    setup
+}
+
+async fn end_stake(setup: &mut Setup) -> Result<(), TransactionError> {
+    let ix = instruction::end_stake(
+        &setup.program_id,
+        &setup.user.pubkey(),
+        &setup.user_token,
+        &setup.stake,
+        &setup.rewards,
+    );
+    send(&mut setup.context, ix, &[&setup.user]).await
+}
+
+#[tokio::test]
+async fn test_paused_staking_rejects_new_stakes() {
+    let mut setup = setup(RewardsPool::PAUSE_STAKING).await;
+    let new_stake = Pubkey::new_unique();
+
+    let ix = instruction::create_stake(
+        &setup.program_id,
+        &setup.user.pubkey(),
+        &setup.user_token,
+        &new_stake,
+        &setup.rewards,
+        STAKE_AMOUNT,
+    );
+    let err = send(&mut setup.context, ix, &[&setup.user])
+        .await
+        .unwrap_err();
+    assert_eq!(err, rejected());
+}
+
+#[tokio::test]
+async fn test_end_stake_pays_rewards_when_not_paused() {
+    let mut setup = setup(0).await;
+
+    end_stake(&mut setup).await.unwrap();
+
+    let reward = reward::accrued_reward(STAKE_AMOUNT, STARTED, STARTED + AN_HOUR);
+    assert!(reward > 0);
+    assert_eq!(
+        token_balance(&mut setup.context.banks_client, &setup.user_token).await,
+        2 * STAKE_AMOUNT + reward
+    );
+}
+
+#[tokio::test]
+async fn test_paused_rewards_return_principal_only() {
+    let mut setup = setup(RewardsPool::PAUSE_STAKING | RewardsPool::PAUSE_REWARDS).await;
+
+    end_stake(&mut setup).await.unwrap();
+
+    assert_eq!(
+        token_balance(&mut setup.context.banks_client, &setup.user_token).await,
+        2 * STAKE_AMOUNT
+    );
+    let pool: RewardsPool = get_state(&mut setup.context.banks_client, &setup.rewards)
+        .await
+        .unwrap();
+    assert_eq!(pool.amount, POOL_AMOUNT);
+    assert_eq!(pool.total_coins_staked, 0);
+    assert_eq!(pool.total_stakes_count, 0);
+}
+
+#[tokio::test]
+async fn test_guardian_pauses_but_cannot_unpause() {
+    let guardian = Keypair::new();
+    let mut setup = setup_with_pool(pool(0), &guardian.pubkey()).await;
+
+    let ix = instruction::set_pause(
+        &setup.program_id,
+        &guardian.pubkey(),
+        &[],
+        &setup.rewards,
+        RewardsPool::PAUSE_AIRDROP | RewardsPool::PAUSE_VOTING,
+    );
+    send(&mut setup.context, ix, &[&guardian]).await.unwrap();
+
+    let ix = instruction::set_pause(
+        &setup.program_id,
+        &guardian.pubkey(),
+        &[],
+        &setup.rewards,
+        RewardsPool::PAUSE_VOTING,
+    );
+    assert_eq!(
+        send(&mut setup.context, ix, &[&guardian])
+            .await
+            .unwrap_err(),
+        rejected()
+    );
+
+    let ix = instruction::set_pause(
+        &setup.program_id,
+        &setup.admin.pubkey(),
+        &[],
+        &setup.rewards,
+        0,
+    );
+    send(&mut setup.context, ix, &[&setup.admin]).await.unwrap();
+
+    let pool: RewardsPool = get_state(&mut setup.context.banks_client, &setup.rewards)
+        .await
+        .unwrap();
+    assert_eq!(pool.paused, 0);
+}
+
+#[tokio::test]
+async fn test_guardian_resumes_pool_once_admin_renounced() {
+    let guardian = Keypair::new();
+    let mut setup = setup_with_pool(pool(0), &guardian.pubkey()).await;
+
+    let ix = instruction::renounce_admin(&setup.program_id, &setup.admin.pubkey(), &[]);
+    send(&mut setup.context, ix, &[&setup.admin]).await.unwrap();
+
+    for paused in [RewardsPool::PAUSE_STAKING | RewardsPool::PAUSE_VOTING, 0] {
+        let ix = instruction::set_pause(
+            &setup.program_id,
+            &guardian.pubkey(),
+            &[],
+            &setup.rewards,
+            paused,
+        );
+        send(&mut setup.context, ix, &[&guardian]).await.unwrap();
+
+        let pool: RewardsPool = get_state(&mut setup.context.banks_client, &setup.rewards)
+            .await
+            .unwrap();
+        assert_eq!(pool.paused, paused);
+    }
+
+    let ix = instruction::set_pause(
+        &setup.program_id,
+        &setup.admin.pubkey(),
+        &[],
+        &setup.rewards,
+        RewardsPool::PAUSE_STAKING,
+    );
+    assert_eq!(
+        send(&mut setup.context, ix, &[&setup.admin])
+            .await
+            .unwrap_err(),
+        rejected()
+    );
+}
+
+#[tokio::test]
+async fn test_set_pause_rejects_other_signers() {
+    let mut setup = setup(0).await;
+
+    let ix = instruction::set_pause(
+        &setup.program_id,
+        &setup.user.pubkey(),
+        &[],
+        &setup.rewards,
+        RewardsPool::PAUSE_STAKING,
+    );
+    assert_eq!(
+        send(&mut setup.context, ix, &[&setup.user])
+            .await
+            .unwrap_err(),
+        rejected()
+    );
+}
+
+#[tokio::test]
+async fn test_emergency_withdraw_returns_principal_while_paused() {
+    let mut setup = setup(u8::MAX).await;
+
+    let ix = instruction::emergency_withdraw(
+        &setup.program_id,
+        &setup.user.pubkey(),
+        &setup.user_token,
+        &setup.stake,
+        &setup.rewards,
+    );
+    send(&mut setup.context, ix, &[&setup.user]).await.unwrap();
+
+    assert_eq!(
+        token_balance(&mut setup.context.banks_client, &setup.user_token).await,
+        2 * STAKE_AMOUNT
+    );
+    let pool: RewardsPool = get_state(&mut setup.context.banks_client, &setup.rewards)
+        .await
+        .unwrap();
+    assert_eq!(pool.amount, POOL_AMOUNT);
+    assert_eq!(pool.total_coins_staked, 0);
+    assert_eq!(pool.total_stakes_count, 0);
+
+    //The stake account is closed.
+    assert!(setup
+        .context
+        .banks_client
+        .get_account(setup.stake)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+async fn emergency_withdraw(setup: &mut Setup) -> StakeEnded {
+    let ix = instruction::emergency_withdraw(
+        &setup.program_id,
+        &setup.user.pubkey(),
+        &setup.user_token,
+        &setup.stake,
+        &setup.rewards,
+    );
+    send(&mut setup.context, ix, &[&setup.user]).await.unwrap();
+
+    assert_eq!(
+        token_balance(&mut setup.context.banks_client, &setup.user_token).await,
+        2 * STAKE_AMOUNT
+    );
+    stubs::return_data(&setup.program_id).unwrap()
+}
+
+#[tokio::test]
+async fn test_emergency_withdraw_leaves_inconsistent_counters_alone() {
+    //The pool never counted the stake.
+    let mut setup = setup_with_pool(rewards_pool(POOL_AMOUNT, 0, 0), &Pubkey::new_unique()).await;
+
+    let ended = emergency_withdraw(&mut setup).await;
+    assert_eq!(ended.payout, STAKE_AMOUNT);
+    assert_eq!(ended.pool_remaining, POOL_AMOUNT);
+
+    let pool: RewardsPool = get_state(&mut setup.context.banks_client, &setup.rewards)
+        .await
+        .unwrap();
+    assert_eq!(pool.total_coins_staked, 0);
+    assert_eq!(pool.total_stakes_count, 0);
+}
+
+#[tokio::test]
+async fn test_emergency_withdraw_refunds_next_to_corrupt_pool() {
+    //A pool this build cannot read.
+    let corrupt = RewardsPool {
+        header: AccountHeader {
+            version: u8::MAX,
+            ..RewardsPool::header()
+        },
+        ..rewards_pool(POOL_AMOUNT, 0, 0)
+    };
+    let mut setup = setup_with_pool(corrupt, &Pubkey::new_unique()).await;
+
+    let ended = emergency_withdraw(&mut setup).await;
+    assert_eq!(ended.payout, STAKE_AMOUNT);
+    assert_eq!(ended.pool_remaining, 0);
+    assert!(setup
+        .context
+        .banks_client
+        .get_account(setup.stake)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[tokio::test]
+async fn test_emergency_withdraw_rejects_account_that_is_not_a_pool() {
+    let mut setup = setup(0).await;
+    //Anyone can create a zeroed account of the pool's size owned by the program.
+    let fake = Keypair::new();
+    let ix = system_instruction::create_account(
+        &setup.context.payer.pubkey(),
+        &fake.pubkey(),
+        LAMPORTS_PER_SOL,
+        RewardsPool::LEN as u64,
+        &setup.program_id,
+    );
+    send(&mut setup.context, ix, &[&fake]).await.unwrap();
+
+    let ix = instruction::emergency_withdraw(
+        &setup.program_id,
+        &setup.user.pubkey(),
+        &setup.user_token,
+        &setup.stake,
+        &fake.pubkey(),
+    );
+    assert_eq!(
+        send(&mut setup.context, ix, &[&setup.user])
+            .await
+            .unwrap_err(),
+        error(InstructionError::InvalidAccountData)
+    );
+    let pool: RewardsPool = get_state(&mut setup.context.banks_client, &setup.rewards)
+        .await
+        .unwrap();
+    assert_eq!(pool.total_coins_staked, STAKE_AMOUNT);
+    assert_eq!(pool.total_stakes_count, 1);
+}
+
+#[tokio::test]
+async fn test_emergency_withdraw_rejects_other_signers() {
+    let mut setup = setup(0).await;
+    let thief = Keypair::new();
+
+    let ix = instruction::emergency_withdraw(
+        &setup.program_id,
+        &thief.pubkey(),
+        &setup.user_token,
+        &setup.stake,
+        &setup.rewards,
+    );
+    assert_eq!(
+        send(&mut setup.context, ix, &[&thief]).await.unwrap_err(),
+        error(InstructionError::IllegalOwner)
+    );
+}
@@ -0,0 +1,199 @@
+mod common;
+
+use common::*;
+use solana_program::instruction::InstructionError;
+use solana_program_test::tokio;
+use solana_sdk::{
+    signature::{Keypair, Signer},
+    transaction::TransactionError,
+};
+use staking::{
+    instruction,
+    processor::BASE_COIN,
+    state::{PoolBucket, RewardsPool},
+};
+
+const REWARDS: u64 = 100 * BASE_COIN;
+const AIRDROP_SUPPLY: u64 = 50 * BASE_COIN;
+const STAKED: u64 = 30 * BASE_COIN;
+
+/// Returns the setup and the pool admin.
+async fn setup(vault_amount: u64) -> (Setup, Keypair) {
+    let setup = SetupBuilder {
+        pool: Some(RewardsPool {
+            total_coins_staked: STAKED,
+            total_stakes_count: 1,
+            ..rewards_pool(REWARDS, AIRDROP_SUPPLY, 0)
+        }),
+        ..SetupBuilder::new(vault_amount)
+    }
+    .start()
+    .await;
+    let admin = Keypair::from_bytes(&setup.admin.to_bytes()).unwrap();
+    (setup, admin)
+}
+
+async fn withdraw(
+    setup: &mut Setup,
+    signer: &Keypair,
+    bucket: PoolBucket,
+    amount: u64,
+) -> Result<(), TransactionError> {
+    let ix = instruction::withdraw_from_pool(
+        &setup.program_id,
+        &signer.pubkey(),
+        &[],
+        &setup.admin_token,
+        &setup.rewards,
+        bucket,
+        amount,
+    );
+    send(&mut setup.context, ix, &[signer]).await
+}
+
+fn insufficient_funds() -> TransactionError {
+    error(InstructionError::InsufficientFunds)
+}
+
+#[tokio::test]
+async fn test_withdraw_from_each_bucket() {
+    let (mut setup, admin) = setup(REWARDS + AIRDROP_SUPPLY + STAKED).await;
+
+    withdraw(&mut setup, &admin, PoolBucket::Rewards, REWARDS)
+        .await
+        .unwrap();
+    withdraw(&mut setup, &admin, PoolBucket::Airdrop, AIRDROP_SUPPLY / 2)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        token_balance(&mut setup.context.banks_client, &setup.admin_token).await,
+        REWARDS + AIRDROP_SUPPLY / 2
+    );
+    assert_eq!(
+        token_balance(
+            &mut setup.context.banks_client,
+            &pda_token_address(&setup.program_id)
+        )
+        .await,
+        AIRDROP_SUPPLY / 2 + STAKED
+    );
+    let pool: RewardsPool = get_state(&mut setup.context.banks_client, &setup.rewards)
+        .await
+        .unwrap();
+    assert_eq!(pool.amount, 0);
+    assert_eq!(pool.airdrop_supply, AIRDROP_SUPPLY / 2);
+    assert_eq!(pool.total_coins_staked, STAKED);
+}
+
+#[tokio::test]
+async fn test_withdraw_limited_to_bucket() {
+    let (mut setup, admin) = setup(REWARDS + AIRDROP_SUPPLY + STAKED).await;
+
+    assert_eq!(
+        withdraw(&mut setup, &admin, PoolBucket::Airdrop, AIRDROP_SUPPLY + 1)
+            .await
+            .unwrap_err(),
+        insufficient_funds()
+    );
+}
+
+#[tokio::test]
+async fn test_withdraw_keeps_staked_principal_backed() {
+    //The vault is short of what the counters claim, staked principal comes first.
+    let (mut setup, admin) = setup(REWARDS + STAKED - BASE_COIN).await;
+
+    assert_eq!(
+        withdraw(&mut setup, &admin, PoolBucket::Rewards, REWARDS)
+            .await
+            .unwrap_err(),
+        insufficient_funds()
+    );
+    withdraw(&mut setup, &admin, PoolBucket::Rewards, REWARDS - BASE_COIN)
+        .await
+        .unwrap();
+    assert_eq!(
+        token_balance(
+            &mut setup.context.banks_client,
+            &pda_token_address(&setup.program_id)
+        )
+        .await,
+        STAKED
+    );
+}
+
+#[tokio::test]
+async fn test_withdraw_requires_pool_admin() {
+    let (mut setup, _admin) = setup(REWARDS + AIRDROP_SUPPLY + STAKED).await;
+    let stranger = Keypair::new();
+
+    assert_eq!(
+        withdraw(&mut setup, &stranger, PoolBucket::Rewards, REWARDS)
+            .await
+            .unwrap_err(),
+        rejected()
+    );
+}
+
+#[tokio::test]
+async fn test_rebalance_moves_counters_only() {
+    let (mut setup, admin) = setup(REWARDS + AIRDROP_SUPPLY + STAKED).await;
+
+    let ix = instruction::rebalance(
+        &setup.program_id,
+        &admin.pubkey(),
+        &[],
+        &setup.rewards,
+        PoolBucket::Airdrop,
+        PoolBucket::Rewards,
+        AIRDROP_SUPPLY,
+    );
+    send(&mut setup.context, ix, &[&admin]).await.unwrap();
+
+    let pool: RewardsPool = get_state(&mut setup.context.banks_client, &setup.rewards)
+        .await
+        .unwrap();
+    assert_eq!(pool.amount, REWARDS + AIRDROP_SUPPLY);
+    assert_eq!(pool.airdrop_supply, 0);
+    assert_eq!(
+        token_balance(
+            &mut setup.context.banks_client,
+            &pda_token_address(&setup.program_id)
+        )
+        .await,
+        REWARDS + AIRDROP_SUPPLY + STAKED
+    );
+}
+
+#[tokio::test]
+async fn test_rebalance_rejects_overdraw_and_same_bucket() {
+    let (mut setup, admin) = setup(REWARDS + AIRDROP_SUPPLY + STAKED).await;
+
+    let ix = instruction::rebalance(
+        &setup.program_id,
+        &admin.pubkey(),
+        &[],
+        &setup.rewards,
+        PoolBucket::Rewards,
+        PoolBucket::Airdrop,
+        REWARDS + 1,
+    );
+    assert_eq!(
+        send(&mut setup.context, ix, &[&admin]).await.unwrap_err(),
+        insufficient_funds()
+    );
+
+    let ix = instruction::rebalance(
+        &setup.program_id,
+        &admin.pubkey(),
+        &[],
+        &setup.rewards,
+        PoolBucket::Rewards,
+        PoolBucket::Rewards,
+        1,
+    );
+    assert_eq!(
+        send(&mut setup.context, ix, &[&admin]).await.unwrap_err(),
+        rejected()
+    );
+}
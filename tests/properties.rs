@@ -0,0 +1,196 @@
+use borsh::BorshSerialize;
+use proptest::prelude::*;
+use solana_program::pubkey::Pubkey;
+use staking::{
+    instruction::Instruction,
+    processor::COINS_FOR_1_INTEREST,
+    reward,
+    state::{AccountHeader, AccountState, Ballot, RewardsPool, Stake},
+    zero_copy::{self, BallotData, RewardsPoolData},
+};
+
+fn stake(amount: u64, time_started: i64) -> Stake {
+    Stake {
+        header: Stake::header(),
+        is_initialized: true,
+        owner: Pubkey::default(),
+        amount,
+        time_started,
+    }
+}
+
+fn pool(amount: u64, total_coins_staked: u64, total_stakes_count: u32, paused: u8) -> RewardsPool {
+    RewardsPool {
+        header: RewardsPool::header(),
+        is_initialized: true,
+        amount,
+        total_coins_staked,
+        total_stakes_count,
+        paused,
+        ..RewardsPool::default()
+    }
+}
+
+fn pool_view(pool: &RewardsPool) -> RewardsPoolData {
+    *zero_copy::view::<RewardsPoolData>(&pool.try_to_vec().unwrap()).unwrap()
+}
+
+fn ballot_view(choices: [u32; 5]) -> BallotData {
+    let ballot = Ballot {
+        header: AccountHeader::default(),
+        is_initialized: true,
+        choices,
+    };
+    *zero_copy::view::<BallotData>(&ballot.try_to_vec().unwrap()).unwrap()
+}
+
+/// Mostly realistic values, with the edges mixed in.
+fn any_amount() -> impl Strategy<Value = u64> {
+    prop_oneof![0..1_000_000_000_000u64, Just(u64::MAX), any::<u64>()]
+}
+
+fn any_time() -> impl Strategy<Value = i64> {
+    prop_oneof![
+        1_600_000_000..1_900_000_000i64,
+        Just(i64::MIN),
+        Just(i64::MAX),
+        any::<i64>()
+    ]
+}
+
+proptest! {
+    #[test]
+    fn unpack_never_panics(data in proptest::collection::vec(any::<u8>(), 0..128)) {
+        let _ = Instruction::unpack(&data);
+    }
+
+    #[test]
+    fn unpack_round_trips(tag in 0u8..25, payload in proptest::collection::vec(any::<u8>(), 0..80)) {
+        let mut data = vec![tag];
+        data.extend(payload);
+        if let Ok(instruction) = Instruction::unpack(&data) {
+            let packed = instruction.pack();
+            prop_assert_eq!(packed[0], tag);
+            prop_assert!(packed.len() <= data.len());
+            prop_assert_eq!(Instruction::unpack(&packed).unwrap().pack(), packed);
+        }
+    }
+
+    #[test]
+    fn accrued_reward_never_panics_and_grows_with_time(
+        amount in any_amount(),
+        time_started in any_time(),
+        now in any_time(),
+        later in 0..i64::MAX,
+    ) {
+        let accrued = reward::accrued_reward(amount, time_started, now);
+        let then = now.saturating_add(later);
+        prop_assert!(reward::accrued_reward(amount, time_started, then) >= accrued);
+        if now <= time_started {
+            prop_assert_eq!(accrued, 0);
+        }
+    }
+
+    #[test]
+    fn accrued_reward_matches_reference(amount in any::<u64>(), seconds in 0..i64::MAX) {
+        //Floor of coin-seconds over coin-seconds per interest, without any fixed point.
+        let per_interest = COINS_FOR_1_INTEREST as u128 * 60;
+        let expected = amount as u128 * seconds as u128 / per_interest;
+        let expected = expected.min(u64::MAX as u128) as u64;
+        prop_assert_eq!(reward::accrued_reward(amount, 0, seconds), expected);
+
+        if let Some(scaled) = (amount as u128 * seconds as u128).checked_mul(reward::PRECISION) {
+            prop_assert_eq!(
+                reward::accrued_reward_scaled(amount, 0, seconds),
+                scaled / per_interest
+            );
+        }
+    }
+
+    #[test]
+    fn simulations_never_panic(
+        amount in any_amount(),
+        pool_amount in any_amount(),
+        total_coins_staked in any_amount(),
+        duration in any_time(),
+        paused in any::<u8>(),
+    ) {
+        let pool = pool(pool_amount, total_coins_staked, 1, paused);
+        let projected = reward::projected_reward(&pool, amount, duration);
+        prop_assert!(projected <= pool_amount);
+        let _ = reward::effective_apr_bps(&pool, amount);
+        let _ = reward::stake_apr_bps(&pool, &stake(amount, 0));
+    }
+
+    #[test]
+    fn settle_stake_conserves_tokens(
+        amount in any_amount(),
+        time_started in any_time(),
+        now in any_time(),
+        pool_amount in any_amount(),
+        total_coins_staked in any_amount(),
+        total_stakes_count in prop_oneof![Just(0u32), any::<u32>()],
+        paused in any::<u8>(),
+    ) {
+        let stake = stake(amount, time_started);
+        let before = pool_view(&pool(pool_amount, total_coins_staked, total_stakes_count, paused));
+        let mut after = before;
+
+        match reward::settle_stake(&stake, &mut after, now) {
+            Ok(settlement) => {
+                prop_assert_eq!(settlement.payout, amount + settlement.reward);
+                prop_assert!(settlement.reward <= settlement.accrued);
+                prop_assert!(settlement.reward <= pool_amount);
+                //Every coin paid out comes off the pool's counters, nothing more.
+                prop_assert_eq!(
+                    after.amount as u128 + after.total_coins_staked as u128 + settlement.payout as u128,
+                    pool_amount as u128 + total_coins_staked as u128
+                );
+                prop_assert_eq!({ after.total_stakes_count }, total_stakes_count - 1);
+                if before.is_paused(RewardsPool::PAUSE_REWARDS) {
+                    prop_assert_eq!(settlement.reward, 0);
+                }
+            }
+            Err(_) => {
+                prop_assert!(
+                    now < time_started
+                        || total_stakes_count == 0
+                        || total_coins_staked < amount
+                        || amount.checked_add(settlement_reward(&stake, &before, now)).is_none()
+                );
+                prop_assert_eq!(bytemuck::bytes_of(&after), bytemuck::bytes_of(&before));
+            }
+        }
+    }
+
+    #[test]
+    fn record_vote_counts_exactly_one_valid_choice(
+        choices in prop_oneof![any::<[u32; 5]>(), Just([u32::MAX; 5])],
+        selection in any::<u8>(),
+    ) {
+        let before = ballot_view(choices);
+        let mut after = before;
+
+        match after.record_vote(selection) {
+            Ok(()) => {
+                let (before, after) = ({ before.choices }, { after.choices });
+                for i in 0..5 {
+                    let expected = if i == selection as usize { before[i] + 1 } else { before[i] };
+                    prop_assert_eq!(after[i], expected);
+                }
+            }
+            Err(_) => {
+                prop_assert!(selection >= 5 || choices[selection as usize] == u32::MAX);
+                prop_assert_eq!({ after.choices }, choices);
+            }
+        }
+    }
+}
+
+fn settlement_reward(stake: &Stake, pool: &RewardsPoolData, now: i64) -> u64 {
+    reward::payable_reward(
+        reward::accrued_reward(stake.amount, stake.time_started, now),
+        pool.amount,
+        pool.paused,
+    )
+}
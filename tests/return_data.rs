@@ -0,0 +1,147 @@
+mod common;
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use common::*;
+use solana_program_test::tokio;
+use solana_sdk::signature::Signer;
+use staking::{
+    instruction,
+    processor::BASE_COIN,
+    return_data::{Airdropped, PoolBalances, Released, StakeCreated, StakeEnded, Voted},
+    reward::{self, RewardSimulation},
+};
+
+const POOL: u64 = 1_000 * BASE_COIN;
+const STAKE: u64 = 100 * BASE_COIN;
+const STARTED: i64 = 1_700_000_000;
+
+fn le(values: &[u64]) -> Vec<u8> {
+    values
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .collect()
+}
+
+#[test]
+fn test_layouts_are_plain_little_endian() {
+    //Calling programs may read these without Borsh, keep the layouts fixed.
+    let ended = StakeEnded {
+        principal: 1,
+        reward: 2,
+        payout: 3,
+        owner_balance: 4,
+        pool_remaining: 5,
+    };
+    assert_eq!(ended.try_to_vec().unwrap(), le(&[1, 2, 3, 4, 5]));
+
+    let created = StakeCreated {
+        amount: 1,
+        owner_balance: 2,
+        total_coins_staked: 3,
+    };
+    assert_eq!(created.try_to_vec().unwrap(), le(&[1, 2, 3]));
+
+    let airdropped = Airdropped {
+        amount: 1,
+        fee: 2,
+        vesting: true,
+        owner_balance: 3,
+        total_claimed: 4,
+        airdrop_remaining: 5,
+    };
+    let mut expected = le(&[1, 2]);
+    expected.push(1);
+    expected.extend(le(&[3, 4, 5]));
+    assert_eq!(airdropped.try_to_vec().unwrap(), expected);
+
+    let released = Released {
+        amount: 1,
+        beneficiary_balance: 2,
+        still_vesting: 3,
+    };
+    assert_eq!(released.try_to_vec().unwrap(), le(&[1, 2, 3]));
+
+    let balances = PoolBalances {
+        rewards: 1,
+        airdrop: 2,
+    };
+    assert_eq!(balances.try_to_vec().unwrap(), le(&[1, 2]));
+
+    let simulation = RewardSimulation {
+        reward: 1,
+        apr_bps: 2,
+    };
+    assert_eq!(simulation.try_to_vec().unwrap(), le(&[1, 2]));
+}
+
+#[test]
+fn test_round_trip() {
+    let voted = Voted {
+        choices: [1, 0, 3, 0, 5],
+        owner_balance: 42,
+    };
+    let data = voted.try_to_vec().unwrap();
+    assert_eq!(data.len(), 5 * 4 + 8);
+    assert_eq!(Voted::try_from_slice(&data).unwrap(), voted);
+}
+
+#[tokio::test]
+async fn test_stake_instructions_return_results() {
+    let setup = SetupBuilder {
+        pool: Some(rewards_pool(POOL, 0, 0)),
+        user_coins: 2 * STAKE,
+        ..SetupBuilder::new(POOL)
+    }
+    .start()
+    .await;
+    let Setup {
+        mut context,
+        program_id,
+        rewards,
+        user,
+        user_token,
+        stake,
+        ..
+    } = setup;
+    stubs::set_unix_timestamp(&program_id, STARTED);
+
+    let ix = instruction::create_stake(
+        &program_id,
+        &user.pubkey(),
+        &user_token,
+        &stake,
+        &rewards,
+        STAKE,
+    );
+    send(&mut context, ix, &[&user]).await.unwrap();
+    assert_eq!(
+        stubs::return_data::<StakeCreated>(&program_id),
+        Some(StakeCreated {
+            amount: STAKE,
+            owner_balance: STAKE,
+            total_coins_staked: STAKE,
+        })
+    );
+
+    let ix = instruction::simulate_reward(&program_id, &rewards, Some(&stake), 0, 3_600);
+    send(&mut context, ix, &[]).await.unwrap();
+    let expected = reward::accrued_reward(STAKE, STARTED, STARTED + 3_600);
+    assert_eq!(
+        stubs::return_data::<RewardSimulation>(&program_id).map(|simulation| simulation.reward),
+        Some(expected)
+    );
+
+    stubs::set_unix_timestamp(&program_id, STARTED + 3_600);
+    let ix = instruction::end_stake(&program_id, &user.pubkey(), &user_token, &stake, &rewards);
+    send(&mut context, ix, &[&user]).await.unwrap();
+    assert_eq!(
+        stubs::return_data::<StakeEnded>(&program_id),
+        Some(StakeEnded {
+            principal: STAKE,
+            reward: expected,
+            payout: STAKE + expected,
+            owner_balance: 2 * STAKE + expected,
+            pool_remaining: POOL - expected,
+        })
+    );
+}
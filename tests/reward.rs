@@ -0,0 +1,187 @@
+mod common;
+
+use common::*;
+use solana_program::pubkey::Pubkey;
+use solana_program_test::tokio;
+use staking::{
+    instruction,
+    processor::{BASE_COIN, COINS_FOR_1_INTEREST},
+    reward::{self, RewardSimulation, SECONDS_PER_YEAR},
+    state::{AccountState, RewardsPool, Stake},
+};
+
+const YEARLY_REWARD: u64 = (SECONDS_PER_YEAR / 60) as u64;
+
+fn stake(amount: u64, time_started: i64) -> Stake {
+    Stake {
+        header: Stake::header(),
+        is_initialized: true,
+        owner: Pubkey::new_unique(),
+        amount,
+        time_started,
+    }
+}
+
+/// Straight from the definition: one interest per `COINS_FOR_1_INTEREST` per minute, rounded
+/// down once at the end.
+fn reference_reward(amount: u64, seconds: u64) -> u128 {
+    amount as u128 * seconds as u128 / (COINS_FOR_1_INTEREST as u128 * 60)
+}
+
+#[test]
+fn test_accrued_reward_counts_every_second() {
+    let amount = 10 * COINS_FOR_1_INTEREST;
+    assert_eq!(reward::accrued_reward(amount, 1_000, 1_000), 0);
+    assert_eq!(reward::accrued_reward(amount, 1_000, 1_005), 0);
+    assert_eq!(reward::accrued_reward(amount, 1_000, 1_006), 1);
+    assert_eq!(reward::accrued_reward(amount, 1_000, 1_059), 9);
+    assert_eq!(reward::accrued_reward(amount, 1_000, 1_060), 10);
+    assert_eq!(reward::accrued_reward(amount, 1_000, 999), 0);
+    //Partial COINS_FOR_1_INTEREST earn their share, rounded down.
+    assert_eq!(
+        reward::accrued_reward(COINS_FOR_1_INTEREST - 1, 0, 3_600),
+        59
+    );
+    assert_eq!(
+        reward::accrued_reward(BASE_COIN, 0, SECONDS_PER_YEAR),
+        243_333
+    );
+}
+
+#[test]
+fn test_accrued_reward_matches_reference() {
+    let amounts = [
+        1,
+        BASE_COIN - 1,
+        BASE_COIN,
+        COINS_FOR_1_INTEREST - 1,
+        COINS_FOR_1_INTEREST + 1,
+        123_456_789 * BASE_COIN,
+        u64::MAX / 2,
+    ];
+    let durations = [
+        0,
+        1,
+        59,
+        61,
+        3_599,
+        86_401,
+        SECONDS_PER_YEAR as u64,
+        10 * SECONDS_PER_YEAR as u64,
+    ];
+    for &amount in amounts.iter() {
+        for &seconds in durations.iter() {
+            let expected = reference_reward(amount, seconds);
+            assert_eq!(
+                reward::accrued_reward(amount, 0, seconds as i64),
+                expected.min(u64::MAX as u128) as u64,
+                "{} coins for {} seconds",
+                amount,
+                seconds
+            );
+            let scaled = reward::accrued_reward_scaled(amount, 0, seconds as i64);
+            assert!(scaled >= expected * reward::PRECISION);
+            assert!(scaled < (expected + 1) * reward::PRECISION);
+        }
+    }
+}
+
+#[test]
+fn test_accrued_reward_scaled_keeps_fractions() {
+    //A base coin for a second is 1 / 129_600_000 of an interest.
+    assert_eq!(reward::accrued_reward_scaled(1, 0, 1), 7_716);
+    assert_eq!(
+        reward::accrued_reward_scaled(COINS_FOR_1_INTEREST, 0, 90),
+        reward::PRECISION * 3 / 2
+    );
+    assert_eq!(
+        reward::accrued_reward_scaled(u64::MAX, i64::MIN, i64::MAX),
+        u128::MAX
+    );
+}
+
+#[test]
+fn test_pending_reward_follows_pool_state() {
+    let stake = stake(10 * COINS_FOR_1_INTEREST, 0);
+    let pool = rewards_pool(1_000, 0, 0);
+    assert_eq!(reward::pending_reward(&stake, &pool, 30 * 60), 300);
+
+    let paused = RewardsPool {
+        paused: RewardsPool::PAUSE_REWARDS,
+        ..rewards_pool(1_000, 0, 0)
+    };
+    assert_eq!(reward::pending_reward(&stake, &paused, 30 * 60), 0);
+
+    let underfunded = rewards_pool(299, 0, 0);
+    assert_eq!(reward::pending_reward(&stake, &underfunded, 30 * 60), 0);
+}
+
+#[test]
+fn test_projected_reward_and_apr() {
+    let amount = 100 * COINS_FOR_1_INTEREST;
+    let funded = rewards_pool(1_000 * amount, 0, 0);
+    assert_eq!(reward::projected_reward(&funded, amount, 3_600), 6_000);
+
+    let nominal = YEARLY_REWARD as u128 * 100 * 10_000 / amount as u128;
+    assert_eq!(reward::effective_apr_bps(&funded, amount) as u128, nominal);
+    assert_eq!(reward::effective_apr_bps(&funded, 0), 0);
+
+    //A pool that cannot fund a year for everyone spreads its balance over all stakes.
+    let thin = RewardsPool {
+        total_coins_staked: amount,
+        ..rewards_pool(amount / 10, 0, 0)
+    };
+    assert_eq!(reward::effective_apr_bps(&thin, amount), 500);
+    assert_eq!(reward::stake_apr_bps(&thin, &stake(amount, 0)), 1_000);
+}
+
+#[tokio::test]
+async fn test_simulate_reward_is_read_only() {
+    let Setup {
+        mut context,
+        program_id,
+        rewards,
+        stake: stake_address,
+        ..
+    } = SetupBuilder {
+        pool: Some(rewards_pool(1_000 * BASE_COIN, 0, 0)),
+        stake_state: Some(stake(100 * BASE_COIN, 0)),
+        ..SetupBuilder::new(0)
+    }
+    .start()
+    .await;
+    stubs::set_unix_timestamp(&program_id, 3_600);
+    let before = context.banks_client.get_account(rewards).await.unwrap();
+    let pool = rewards_pool(1_000 * BASE_COIN, 0, 0);
+
+    let ix = instruction::simulate_reward(&program_id, &rewards, None, 100 * BASE_COIN, 3_600);
+    assert!(ix.accounts.iter().all(|account| !account.is_writable));
+    send(&mut context, ix, &[]).await.unwrap();
+    assert_eq!(
+        stubs::return_data::<RewardSimulation>(&program_id),
+        Some(RewardSimulation {
+            reward: reward::projected_reward(&pool, 100 * BASE_COIN, 3_600),
+            apr_bps: reward::effective_apr_bps(&pool, 100 * BASE_COIN),
+        })
+    );
+
+    //A stake's simulation starts from its own start time, and ignores the amount.
+    let ix = instruction::simulate_reward(&program_id, &rewards, Some(&stake_address), 0, 60);
+    send(&mut context, ix, &[]).await.unwrap();
+    let stake = stake(100 * BASE_COIN, 0);
+    let simulation = RewardSimulation {
+        reward: reward::pending_reward(&stake, &pool, 3_660),
+        apr_bps: reward::stake_apr_bps(&pool, &stake),
+    };
+    assert_eq!(
+        stubs::return_data::<RewardSimulation>(&program_id),
+        Some(simulation)
+    );
+    assert!(simulation.reward > 0 && simulation.apr_bps > 0);
+
+    let after = context.banks_client.get_account(rewards).await.unwrap();
+    assert_eq!(before, after);
+
+    let ix = instruction::simulate_reward(&program_id, &rewards, None, BASE_COIN, -1);
+    assert_eq!(send(&mut context, ix, &[]).await.unwrap_err(), rejected());
+}
@@ -0,0 +1,397 @@
+mod common;
+
+use common::*;
+use solana_program::{instruction::InstructionError, pubkey::Pubkey};
+use solana_program_test::tokio;
+use solana_sdk::{
+    signature::{Keypair, Signer},
+    transaction::TransactionError,
+};
+use staking::{
+    instruction,
+    processor::BASE_COIN,
+    reward,
+    state::{AccountState, RewardsPool, Stake},
+};
+const REWARDS: u64 = 1_000 * BASE_COIN;
+const AIRDROP_SUPPLY: u64 = 500 * BASE_COIN;
+const STAKE_AMOUNT: u64 = 100 * BASE_COIN;
+const AN_HOUR: i64 = 3600;
+const STARTED: i64 = 1_700_000_000;
+
+/// An empty vault, a zeroed pool and stake account, the pool admin holding the rewards and a
+/// user holding two stakes worth of coins.
+async fn setup() -> Setup {
+    builder(0).start().await
+}
+
+/// A pool holding `pool_amount` in rewards and the user's stake, started at `STARTED` with
+/// the program's clock an hour later.
+async fn setup_with_aged_stake(pool_amount: u64) -> Setup {
+    let builder = builder(pool_amount + STAKE_AMOUNT);
+    let stake = Stake {
+        header: Stake::header(),
+        is_initialized: true,
+        owner: builder.user.pubkey(),
+        amount: STAKE_AMOUNT,
+        time_started: STARTED,
+    };
+    let setup = SetupBuilder {
+        pool: Some(RewardsPool {
+            total_coins_staked: STAKE_AMOUNT,
+            total_stakes_count: 1,
+            ..rewards_pool(pool_amount, 0, 0)
+        }),
+        stake_state: Some(stake),
+        ..builder
+    }
+    .start()
+    .await;
+    stubs::set_unix_timestamp(&setup.program_id, STARTED + AN_HOUR);
+    setup
+}
+
+fn builder(vault_amount: u64) -> SetupBuilder {
+    SetupBuilder {
+        pool: None,
+        admin_coins: REWARDS + AIRDROP_SUPPLY,
+        user_coins: 2 * STAKE_AMOUNT,
+        ..SetupBuilder::new(vault_amount)
+    }
+}
+
+async fn add_rewards(
+    setup: &mut Setup,
+    donator: &Keypair,
+    donator_token: &Pubkey,
+    amount: u64,
+    airdrop: bool,
+) -> Result<(), TransactionError> {
+    let ix = instruction::add_to_rewards_pool(
+        &setup.program_id,
+        &donator.pubkey(),
+        donator_token,
+        &setup.rewards,
+        amount,
+        airdrop,
+    );
+    send(&mut setup.context, ix, &[donator]).await
+}
+
+/// Initializes the pool with `REWARDS` and `AIRDROP_SUPPLY`.
+async fn fund_pool(setup: &mut Setup) {
+    let admin = Keypair::from_bytes(&setup.admin.to_bytes()).unwrap();
+    let admin_token = setup.admin_token;
+    add_rewards(setup, &admin, &admin_token, REWARDS, false)
+        .await
+        .unwrap();
+    add_rewards(setup, &admin, &admin_token, AIRDROP_SUPPLY, true)
+        .await
+        .unwrap();
+}
+
+async fn create_stake(
+    setup: &mut Setup,
+    stake: &Pubkey,
+    amount: u64,
+) -> Result<(), TransactionError> {
+    let ix = instruction::create_stake(
+        &setup.program_id,
+        &setup.user.pubkey(),
+        &setup.user_token,
+        stake,
+        &setup.rewards,
+        amount,
+    );
+    send(&mut setup.context, ix, &[&setup.user]).await
+}
+
+async fn end_stake(setup: &mut Setup, owner: &Keypair) -> Result<(), TransactionError> {
+    let ix = instruction::end_stake(
+        &setup.program_id,
+        &owner.pubkey(),
+        &setup.user_token,
+        &setup.stake,
+        &setup.rewards,
+    );
+    send(&mut setup.context, ix, &[owner]).await
+}
+
+/// Moves to a later slot so that resending a transaction is not deduplicated.
+async fn new_blockhash(setup: &mut Setup) {
+    let slot = setup.context.banks_client.get_root_slot().await.unwrap();
+    setup.context.warp_to_slot(slot + 2).unwrap();
+}
+
+async fn pool(setup: &mut Setup) -> RewardsPool {
+    get_state(&mut setup.context.banks_client, &setup.rewards)
+        .await
+        .unwrap()
+}
+
+async fn vault_balance(setup: &mut Setup) -> u64 {
+    let vault = pda_token_address(&setup.program_id);
+    token_balance(&mut setup.context.banks_client, &vault).await
+}
+
+#[tokio::test]
+async fn test_add_to_rewards_pool_initializes_and_funds_buckets() {
+    let mut setup = setup().await;
+
+    fund_pool(&mut setup).await;
+
+    let pool = pool(&mut setup).await;
+    assert!(pool.is_initialized);
+    assert_eq!(pool.header, RewardsPool::header());
+    assert_eq!(pool.amount, REWARDS);
+    assert_eq!(pool.airdrop_supply, AIRDROP_SUPPLY);
+    assert_eq!(pool.airdrop_treasury, setup.admin.pubkey());
+    assert_eq!(vault_balance(&mut setup).await, REWARDS + AIRDROP_SUPPLY);
+    assert_eq!(
+        token_balance(&mut setup.context.banks_client, &setup.admin_token).await,
+        0
+    );
+}
+
+#[tokio::test]
+async fn test_anyone_donates_to_initialized_pool() {
+    let mut setup = setup().await;
+    let user = Keypair::from_bytes(&setup.user.to_bytes()).unwrap();
+    let user_token = setup.user_token;
+
+    //Only the pool admin sets the pool up.
+    assert_eq!(
+        add_rewards(&mut setup, &user, &user_token, BASE_COIN, false)
+            .await
+            .unwrap_err(),
+        error(InstructionError::InvalidInstructionData)
+    );
+
+    fund_pool(&mut setup).await;
+    add_rewards(&mut setup, &user, &user_token, 2 * BASE_COIN, false)
+        .await
+        .unwrap();
+
+    assert_eq!(pool(&mut setup).await.amount, REWARDS + 2 * BASE_COIN);
+    assert_eq!(
+        vault_balance(&mut setup).await,
+        REWARDS + AIRDROP_SUPPLY + 2 * BASE_COIN
+    );
+}
+
+#[tokio::test]
+async fn test_add_to_rewards_pool_rejects_wrong_vault() {
+    let mut setup = setup().await;
+
+    let mut ix = instruction::add_to_rewards_pool(
+        &setup.program_id,
+        &setup.admin.pubkey(),
+        &setup.admin_token,
+        &setup.rewards,
+        REWARDS,
+        false,
+    );
+    ix.accounts[2].pubkey = setup.user_token;
+    assert_eq!(
+        send(&mut setup.context, ix, &[&setup.admin])
+            .await
+            .unwrap_err(),
+        error(InstructionError::InvalidAccountData)
+    );
+}
+
+#[tokio::test]
+async fn test_create_stake_moves_principal_and_counts_stake() {
+    let mut setup = setup().await;
+    fund_pool(&mut setup).await;
+    let stake = setup.stake;
+
+    stubs::set_unix_timestamp(&setup.program_id, STARTED);
+    create_stake(&mut setup, &stake, STAKE_AMOUNT)
+        .await
+        .unwrap();
+
+    let state: Stake = get_state(&mut setup.context.banks_client, &setup.stake)
+        .await
+        .unwrap();
+    assert!(state.is_initialized);
+    assert_eq!(state.owner, setup.user.pubkey());
+    assert_eq!(state.amount, STAKE_AMOUNT);
+    assert_eq!(state.time_started, STARTED);
+
+    let pool = pool(&mut setup).await;
+    assert_eq!(pool.total_coins_staked, STAKE_AMOUNT);
+    assert_eq!(pool.total_stakes_count, 1);
+    assert_eq!(pool.amount, REWARDS);
+    assert_eq!(
+        token_balance(&mut setup.context.banks_client, &setup.user_token).await,
+        STAKE_AMOUNT
+    );
+    assert_eq!(
+        vault_balance(&mut setup).await,
+        REWARDS + AIRDROP_SUPPLY + STAKE_AMOUNT
+    );
+}
+
+#[tokio::test]
+async fn test_create_stake_errors() {
+    let mut setup = setup().await;
+    let stake = setup.stake;
+
+    //The pool is not set up yet, its data is still zeroed.
+    assert_eq!(
+        create_stake(&mut setup, &stake, STAKE_AMOUNT - 1)
+            .await
+            .unwrap_err(),
+        error(InstructionError::InvalidAccountData)
+    );
+
+    fund_pool(&mut setup).await;
+    assert_eq!(
+        create_stake(&mut setup, &stake, BASE_COIN - 1)
+            .await
+            .unwrap_err(),
+        error(InstructionError::InvalidInstructionData)
+    );
+
+    let mut ix = instruction::create_stake(
+        &setup.program_id,
+        &setup.user.pubkey(),
+        &setup.user_token,
+        &stake,
+        &setup.rewards,
+        STAKE_AMOUNT,
+    );
+    ix.accounts[3].pubkey = setup.admin_token;
+    assert_eq!(
+        send(&mut setup.context, ix, &[&setup.user])
+            .await
+            .unwrap_err(),
+        error(InstructionError::InvalidAccountData)
+    );
+
+    //More than the user holds, the token program refuses the transfer.
+    assert_eq!(
+        create_stake(&mut setup, &stake, 3 * STAKE_AMOUNT)
+            .await
+            .unwrap_err(),
+        error(InstructionError::Custom(
+            spl_token::error::TokenError::InsufficientFunds as u32
+        ))
+    );
+
+    create_stake(&mut setup, &stake, STAKE_AMOUNT)
+        .await
+        .unwrap();
+    assert_eq!(
+        create_stake(&mut setup, &stake, STAKE_AMOUNT + 1)
+            .await
+            .unwrap_err(),
+        error(InstructionError::AccountAlreadyInitialized)
+    );
+}
+
+#[tokio::test]
+async fn test_end_stake_pays_accrued_reward() {
+    let mut setup = setup_with_aged_stake(REWARDS).await;
+    let stake_rent = lamports(&mut setup.context.banks_client, &setup.stake).await;
+    let user_lamports = lamports(&mut setup.context.banks_client, &setup.user.pubkey()).await;
+
+    let user = Keypair::from_bytes(&setup.user.to_bytes()).unwrap();
+    end_stake(&mut setup, &user).await.unwrap();
+
+    let reward = reward::accrued_reward(STAKE_AMOUNT, STARTED, STARTED + AN_HOUR);
+    assert!(reward > 0);
+    assert_eq!(
+        token_balance(&mut setup.context.banks_client, &setup.user_token).await,
+        3 * STAKE_AMOUNT + reward
+    );
+
+    let pool = pool(&mut setup).await;
+    assert_eq!(pool.amount, REWARDS - reward);
+    assert_eq!(pool.total_coins_staked, 0);
+    assert_eq!(pool.total_stakes_count, 0);
+    assert_eq!(vault_balance(&mut setup).await, REWARDS - reward);
+    //The stake account is closed and its rent goes to the owner.
+    assert_eq!(
+        lamports(&mut setup.context.banks_client, &setup.stake).await,
+        0
+    );
+    assert_eq!(
+        lamports(&mut setup.context.banks_client, &setup.user.pubkey()).await,
+        user_lamports + stake_rent
+    );
+}
+
+#[tokio::test]
+async fn test_end_stake_returns_principal_when_pool_is_short() {
+    //An hour of the stake is worth more than the single base coin in the pool.
+    let mut setup = setup_with_aged_stake(1).await;
+
+    let user = Keypair::from_bytes(&setup.user.to_bytes()).unwrap();
+    end_stake(&mut setup, &user).await.unwrap();
+
+    assert_eq!(
+        token_balance(&mut setup.context.banks_client, &setup.user_token).await,
+        3 * STAKE_AMOUNT
+    );
+    let pool = pool(&mut setup).await;
+    assert_eq!(pool.amount, 1);
+    assert_eq!(pool.total_coins_staked, 0);
+}
+
+#[tokio::test]
+async fn test_end_stake_errors() {
+    let mut setup = setup().await;
+    fund_pool(&mut setup).await;
+    let user = Keypair::from_bytes(&setup.user.to_bytes()).unwrap();
+
+    //Nothing staked yet.
+    assert_eq!(
+        end_stake(&mut setup, &user).await.unwrap_err(),
+        error(InstructionError::InvalidAccountData)
+    );
+    new_blockhash(&mut setup).await;
+
+    let stake = setup.stake;
+    create_stake(&mut setup, &stake, STAKE_AMOUNT)
+        .await
+        .unwrap();
+
+    let other = Keypair::new();
+    assert_eq!(
+        end_stake(&mut setup, &other).await.unwrap_err(),
+        error(InstructionError::IllegalOwner)
+    );
+
+    let mut ix = instruction::end_stake(
+        &setup.program_id,
+        &user.pubkey(),
+        &setup.user_token,
+        &setup.stake,
+        &setup.rewards,
+    );
+    ix.accounts[0].is_signer = false;
+    assert_eq!(
+        send(&mut setup.context, ix, &[]).await.unwrap_err(),
+        error(InstructionError::MissingRequiredSignature)
+    );
+
+    let mut ix = instruction::end_stake(
+        &setup.program_id,
+        &user.pubkey(),
+        &setup.user_token,
+        &setup.stake,
+        &setup.rewards,
+    );
+    ix.accounts[4].pubkey = setup.admin_token;
+    assert_eq!(
+        send(&mut setup.context, ix, &[&user]).await.unwrap_err(),
+        error(InstructionError::InvalidAccountData)
+    );
+
+    //A closed stake cannot be ended twice.
+    end_stake(&mut setup, &user).await.unwrap();
+    new_blockhash(&mut setup).await;
+    assert!(end_stake(&mut setup, &user).await.is_err());
+}
@@ -0,0 +1,312 @@
+mod common;
+
+use common::*;
+use solana_program::{instruction::InstructionError, pubkey::Pubkey};
+use solana_program_test::tokio;
+use solana_sdk::{
+    signature::{Keypair, Signer},
+    transaction::TransactionError,
+};
+use staking::{
+    get_vesting_contract_address, instruction,
+    processor::BASE_COIN,
+    state::{AccountState, VestingContract},
+};
+const TOTAL: u64 = 1000 * BASE_COIN;
+const NEW_ID: u64 = 8;
+//The time the program sees in every test.
+const NOW: i64 = 1_700_000_000;
+
+/// Starts a bank at `NOW` holding one funded contract of the user whose times are relative to it,
+/// and the pool admin with `TOTAL` coins for another one.
+async fn setup(start: i64, cliff: i64, end: i64) -> Setup {
+    start_at_now(builder(start, cliff, end)).await
+}
+
+fn builder(start: i64, cliff: i64, end: i64) -> SetupBuilder {
+    let mut builder = SetupBuilder {
+        admin_coins: TOTAL,
+        ..SetupBuilder::new(0)
+    };
+    let program_id = builder.program_id;
+    let beneficiary = builder.user.pubkey();
+    let program_test = &mut builder.program_test;
+
+    //The vault of the contract the admin creates in the tests.
+    let new_vesting = get_vesting_contract_address(&program_id, &beneficiary, NEW_ID);
+    add_token_account(program_test, &vault(&new_vesting), &new_vesting, 0);
+
+    let vesting = get_vesting_contract_address(&program_id, &beneficiary, 7);
+    add_token_account(program_test, &vault(&vesting), &vesting, TOTAL);
+    add_program_account(
+        program_test,
+        &program_id,
+        &vesting,
+        &VestingContract {
+            header: VestingContract::header(),
+            is_initialized: true,
+            beneficiary,
+            id: 7,
+            start_time: NOW + start,
+            cliff_time: NOW + cliff,
+            end_time: NOW + end,
+            total: TOTAL,
+            released: 0,
+            revocable: true,
+        },
+    );
+    builder
+}
+
+async fn start_at_now(builder: SetupBuilder) -> Setup {
+    let setup = builder.start().await;
+    stubs::set_unix_timestamp(&setup.program_id, NOW);
+    setup
+}
+
+fn vault(vesting: &Pubkey) -> Pubkey {
+    spl_associated_token_account::get_associated_token_address(vesting, &kittycoin_mint())
+}
+
+/// The contract `setup` starts with.
+fn vesting(setup: &Setup) -> Pubkey {
+    get_vesting_contract_address(&setup.program_id, &setup.user.pubkey(), 7)
+}
+
+async fn withdraw(setup: &mut Setup, signer: &Keypair) -> Result<(), TransactionError> {
+    let mut ix = instruction::withdraw(
+        &setup.program_id,
+        &setup.user.pubkey(),
+        &setup.user_token,
+        7,
+    );
+    ix.accounts[0].pubkey = signer.pubkey();
+    send(&mut setup.context, ix, &[signer]).await
+}
+
+#[tokio::test]
+async fn test_withdraw_releases_vested_portion() {
+    let mut setup = setup(-1800, -1740, 1800).await;
+    let beneficiary = Keypair::from_bytes(&setup.user.to_bytes()).unwrap();
+
+    withdraw(&mut setup, &beneficiary).await.unwrap();
+
+    let vesting_address = vesting(&setup);
+    let banks_client = &mut setup.context.banks_client;
+    let vesting: VestingContract = get_state(banks_client, &vesting_address).await.unwrap();
+    //Halfway from start to end.
+    assert_eq!(vesting.released, TOTAL / 2);
+    assert_eq!(
+        token_balance(banks_client, &setup.user_token).await,
+        TOTAL / 2
+    );
+}
+
+#[tokio::test]
+async fn test_withdraw_after_end_releases_everything() {
+    let mut setup = setup(-3600, -3600, -60).await;
+    let beneficiary = Keypair::from_bytes(&setup.user.to_bytes()).unwrap();
+
+    withdraw(&mut setup, &beneficiary).await.unwrap();
+
+    let vesting_address = vesting(&setup);
+    let banks_client = &mut setup.context.banks_client;
+    assert_eq!(token_balance(banks_client, &setup.user_token).await, TOTAL);
+    let vesting: VestingContract = get_state(banks_client, &vesting_address).await.unwrap();
+    assert_eq!(vesting.released, TOTAL);
+}
+
+#[tokio::test]
+async fn test_withdraw_rejects_before_cliff() {
+    let mut setup = setup(-60, 3600, 7200).await;
+    let beneficiary = Keypair::from_bytes(&setup.user.to_bytes()).unwrap();
+
+    assert_eq!(
+        withdraw(&mut setup, &beneficiary).await.unwrap_err(),
+        rejected()
+    );
+    assert_eq!(
+        token_balance(&mut setup.context.banks_client, &setup.user_token).await,
+        0
+    );
+}
+
+#[tokio::test]
+async fn test_withdraw_rejects_other_signer() {
+    let mut setup = setup(-3600, -3600, -60).await;
+
+    assert_eq!(
+        withdraw(&mut setup, &Keypair::new()).await.unwrap_err(),
+        error(InstructionError::IllegalOwner)
+    );
+    assert_eq!(
+        token_balance(&mut setup.context.banks_client, &setup.user_token).await,
+        0
+    );
+}
+
+async fn create_vesting(
+    setup: &mut Setup,
+    admin: &Keypair,
+    start: i64,
+    cliff: i64,
+    end: i64,
+    amount: u64,
+) -> Result<(), TransactionError> {
+    let ix = instruction::create_vesting(
+        &setup.program_id,
+        &admin.pubkey(),
+        &setup.admin_token,
+        &setup.user.pubkey(),
+        NEW_ID,
+        start,
+        cliff,
+        end,
+        amount,
+        true,
+    );
+    send(&mut setup.context, ix, &[admin]).await
+}
+
+#[tokio::test]
+async fn test_create_vesting_funds_new_contract() {
+    let mut setup = setup(-3600, -3600, -60).await;
+    let admin = Keypair::from_bytes(&setup.admin.to_bytes()).unwrap();
+
+    create_vesting(&mut setup, &admin, NOW, NOW + 60, NOW + 3600, TOTAL)
+        .await
+        .unwrap();
+
+    let vesting_address =
+        get_vesting_contract_address(&setup.program_id, &setup.user.pubkey(), NEW_ID);
+    let banks_client = &mut setup.context.banks_client;
+    let vesting: VestingContract = get_state(banks_client, &vesting_address).await.unwrap();
+    assert!(vesting.is_initialized);
+    assert_eq!(vesting.beneficiary, setup.user.pubkey());
+    assert_eq!(vesting.id, NEW_ID);
+    assert_eq!(
+        (vesting.start_time, vesting.cliff_time, vesting.end_time),
+        (NOW, NOW + 60, NOW + 3600)
+    );
+    assert_eq!(vesting.total, TOTAL);
+    assert_eq!(vesting.released, 0);
+    let vault = spl_associated_token_account::get_associated_token_address(
+        &vesting_address,
+        &kittycoin_mint(),
+    );
+    assert_eq!(token_balance(banks_client, &vault).await, TOTAL);
+    assert_eq!(token_balance(banks_client, &setup.admin_token).await, 0);
+
+    //The contract address is taken.
+    assert_eq!(
+        create_vesting(&mut setup, &admin, NOW, NOW + 60, NOW + 3600, 1)
+            .await
+            .unwrap_err(),
+        error(InstructionError::AccountAlreadyInitialized)
+    );
+}
+
+#[tokio::test]
+async fn test_create_vesting_at_a_prefunded_address() {
+    let mut builder = builder(-3600, -3600, -60);
+    let vesting_address =
+        get_vesting_contract_address(&builder.program_id, &builder.user.pubkey(), NEW_ID);
+    //Anyone can send lamports to the address of a contract they expect to be created.
+    add_system_account(&mut builder.program_test, &vesting_address, 1);
+    let mut setup = start_at_now(builder).await;
+    let admin = Keypair::from_bytes(&setup.admin.to_bytes()).unwrap();
+
+    create_vesting(&mut setup, &admin, NOW, NOW + 60, NOW + 3600, TOTAL)
+        .await
+        .unwrap();
+
+    let banks_client = &mut setup.context.banks_client;
+    let rent = banks_client.get_rent().await.unwrap();
+    assert_eq!(
+        lamports(banks_client, &vesting_address).await,
+        rent.minimum_balance(VestingContract::LEN)
+    );
+    let vesting: VestingContract = get_state(banks_client, &vesting_address).await.unwrap();
+    assert!(vesting.is_initialized);
+    assert_eq!(vesting.total, TOTAL);
+    assert_eq!(
+        token_balance(banks_client, &vault(&vesting_address)).await,
+        TOTAL
+    );
+}
+
+#[tokio::test]
+async fn test_create_vesting_errors() {
+    let mut setup = setup(-3600, -3600, -60).await;
+    let admin = Keypair::from_bytes(&setup.admin.to_bytes()).unwrap();
+
+    let other = Keypair::new();
+    assert_eq!(
+        create_vesting(&mut setup, &other, NOW, NOW, NOW + 60, TOTAL)
+            .await
+            .unwrap_err(),
+        rejected()
+    );
+    for (start, cliff, end, amount) in [
+        (NOW, NOW, NOW + 60, 0),
+        (NOW, NOW + 120, NOW + 60, TOTAL),
+        (NOW + 60, NOW, NOW + 120, TOTAL),
+        (NOW, NOW, NOW, TOTAL),
+        (-1, NOW, NOW + 60, TOTAL),
+    ] {
+        assert_eq!(
+            create_vesting(&mut setup, &admin, start, cliff, end, amount)
+                .await
+                .unwrap_err(),
+            rejected()
+        );
+    }
+}
+
+async fn revoke(setup: &mut Setup, admin: &Keypair) -> Result<(), TransactionError> {
+    let ix = instruction::revoke_vesting(
+        &setup.program_id,
+        &admin.pubkey(),
+        &[],
+        &setup.admin_token,
+        &setup.user.pubkey(),
+        7,
+    );
+    send(&mut setup.context, ix, &[admin]).await
+}
+
+#[tokio::test]
+async fn test_revoke_vesting_returns_unvested_coins() {
+    let mut setup = setup(-1800, -1800, 1800).await;
+    let admin = Keypair::from_bytes(&setup.admin.to_bytes()).unwrap();
+
+    assert_eq!(
+        revoke(&mut setup, &Keypair::new()).await.unwrap_err(),
+        rejected()
+    );
+    revoke(&mut setup, &admin).await.unwrap();
+
+    let vesting_address = vesting(&setup);
+    let banks_client = &mut setup.context.banks_client;
+    let vesting: VestingContract = get_state(banks_client, &vesting_address).await.unwrap();
+    //Half of it vested, the other half goes back to the admin.
+    assert_eq!(
+        token_balance(banks_client, &setup.admin_token).await,
+        TOTAL + TOTAL / 2
+    );
+    assert_eq!(vesting.total, TOTAL / 2);
+    assert!(!vesting.revocable);
+
+    //What vested stays with the beneficiary.
+    let beneficiary = Keypair::from_bytes(&setup.user.to_bytes()).unwrap();
+    withdraw(&mut setup, &beneficiary).await.unwrap();
+    assert_eq!(
+        token_balance(&mut setup.context.banks_client, &setup.user_token).await,
+        TOTAL / 2
+    );
+
+    //A revoked contract is no longer revocable. Move on a slot so the retry is a new transaction.
+    let slot = setup.context.banks_client.get_root_slot().await.unwrap();
+    setup.context.warp_to_slot(slot + 2).unwrap();
+    assert_eq!(revoke(&mut setup, &admin).await.unwrap_err(), rejected());
+}
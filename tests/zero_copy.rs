@@ -0,0 +1,122 @@
+mod common;
+
+use borsh::BorshSerialize;
+use common::*;
+use solana_program::pubkey::Pubkey;
+use solana_program_test::tokio;
+use solana_sdk::{
+    signature::{Keypair, Signer},
+    transaction::TransactionError,
+};
+use staking::{
+    instruction,
+    processor::BASE_COIN,
+    state::{AccountState, Ballot, PoolBucket, RewardsPool},
+    zero_copy::{self, BallotData, RewardsPoolData},
+};
+
+const VOTING_FEE: u64 = 1000 * BASE_COIN;
+
+#[test]
+fn test_views_match_borsh_layout() {
+    assert_eq!(std::mem::size_of::<RewardsPoolData>(), RewardsPool::LEN);
+    assert_eq!(std::mem::size_of::<BallotData>(), Ballot::LEN);
+
+    let pool = RewardsPool {
+        total_coins_staked: 7,
+        total_stakes_count: 3,
+        airdrop_treasury: Pubkey::new_unique(),
+        vesting_locked: 11,
+        paused: RewardsPool::PAUSE_VOTING,
+        ..rewards_pool(100, 200, 5)
+    };
+    let data = pool.try_to_vec().unwrap();
+    let view = zero_copy::view::<RewardsPoolData>(&data).unwrap();
+    assert!(view.is_initialized());
+    assert_eq!({ view.amount }, 100);
+    assert_eq!({ view.airdrop_supply }, 200);
+    assert_eq!({ view.airdrop_fee }, 5);
+    assert_eq!({ view.total_coins_staked }, 7);
+    assert_eq!({ view.total_stakes_count }, 3);
+    assert_eq!({ view.airdrop_treasury }, pool.airdrop_treasury);
+    assert_eq!({ view.vesting_locked }, 11);
+    assert!(view.is_paused(RewardsPool::PAUSE_VOTING));
+    assert_eq!(view.header.discriminator, RewardsPool::DISCRIMINATOR);
+    for bucket in [PoolBucket::Rewards, PoolBucket::Airdrop] {
+        assert_eq!(view.bucket(bucket), pool.bucket(bucket));
+    }
+
+    let ballot = Ballot {
+        header: Ballot::header(),
+        is_initialized: true,
+        choices: [1, 2, 3, 4, 5],
+    };
+    let data = ballot.try_to_vec().unwrap();
+    let view = zero_copy::view::<BallotData>(&data).unwrap();
+    assert!(view.is_initialized());
+    assert_eq!({ view.choices }, [1, 2, 3, 4, 5]);
+
+    assert!(zero_copy::view::<BallotData>(&data[1..]).is_err());
+}
+
+const BALLOT: Pubkey = Pubkey::new_from_array([3; 32]);
+
+/// Returns the setup and the voter, who holds every role and two voting fees.
+async fn setup() -> (Setup, Keypair) {
+    let mut builder = SetupBuilder {
+        admin_coins: 2 * VOTING_FEE,
+        ..SetupBuilder::new(0)
+    };
+    add_raw_account(
+        &mut builder.program_test,
+        &builder.program_id,
+        &BALLOT,
+        vec![0; Ballot::LEN],
+    );
+    let setup = builder.start().await;
+    let voter = Keypair::from_bytes(&setup.admin.to_bytes()).unwrap();
+    (setup, voter)
+}
+
+async fn vote(setup: &mut Setup, voter: &Keypair, selection: u8) -> Result<(), TransactionError> {
+    let ix = instruction::vote(
+        &setup.program_id,
+        &voter.pubkey(),
+        &setup.admin_token,
+        &BALLOT,
+        &setup.rewards,
+        selection,
+    );
+    send(&mut setup.context, ix, &[voter]).await
+}
+
+#[tokio::test]
+async fn test_vote_updates_ballot_and_pool_in_place() {
+    let (mut setup, voter) = setup().await;
+
+    vote(&mut setup, &voter, 2).await.unwrap();
+    vote(&mut setup, &voter, 4).await.unwrap();
+
+    let ballot: Ballot = get_state(&mut setup.context.banks_client, &BALLOT)
+        .await
+        .unwrap();
+    assert_eq!(ballot.header, Ballot::header());
+    assert!(ballot.is_initialized);
+    assert_eq!(ballot.choices, [0, 0, 1, 0, 1]);
+
+    let pool: RewardsPool = get_state(&mut setup.context.banks_client, &setup.rewards)
+        .await
+        .unwrap();
+    assert_eq!(pool.amount, 2 * VOTING_FEE);
+    assert_eq!(
+        token_balance(&mut setup.context.banks_client, &setup.admin_token).await,
+        0
+    );
+}
+
+#[tokio::test]
+async fn test_vote_rejects_unknown_choice() {
+    let (mut setup, voter) = setup().await;
+
+    assert_eq!(vote(&mut setup, &voter, 5).await.unwrap_err(), rejected());
+}